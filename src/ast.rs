@@ -1,17 +1,44 @@
 use super::{
     comment::{Comment, Comments},
+    error::{Error, Result},
+    escape::Span,
     key::Key,
     merge::Merge,
+    validate::Validator,
     value::{Item, Table, Value},
 };
 pub(crate) use crate::format::independent::Kind;
-use std::{iter::FromIterator, mem::take, vec::Vec};
+use std::{convert::TryFrom, iter::FromIterator, mem::take, vec::Vec};
 
 /// Lines.
-pub struct Lines<'a>(Vec<Line<'a>>);
+pub struct Lines<'a>(pub(crate) Vec<Line<'a>>);
 
-impl From<Lines<'_>> for Table {
-    fn from(from: Lines) -> Self {
+impl Lines<'_> {
+    /// Validates the key definitions of the lines.
+    pub(crate) fn validate(&self) -> Result<()> {
+        let mut validator = Validator::default();
+        for line in &self.0 {
+            match &line.data {
+                Some(Data::Header(key)) => {
+                    let span = line.spans.data.unwrap_or_else(Span::new);
+                    validator.header(key.as_ref(), span)?;
+                }
+                Some(Data::KeyValue { key, .. }) => {
+                    let span = line.spans.key_value.map_or_else(Span::new, |(key, _)| key);
+                    validator.key_value(key, span)?;
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<Lines<'_>> for Table {
+    type Error = Error;
+
+    fn try_from(from: Lines) -> Result<Self> {
+        from.validate()?;
         let mut state = State::new();
         let comments = &mut Comments::new();
         for line in from.0 {
@@ -28,14 +55,14 @@ impl From<Lines<'_>> for Table {
                 Some(Data::KeyValue { key, value }) => {
                     comments.maybe_push(line.meta);
                     let value = Value::wrap(key, Item::new(take(comments), value));
-                    state.table_mut().merge(value);
+                    state.table_mut().merge(value).expect("validated key-value");
                 }
                 _ => {
                     comments.maybe_push(line.meta);
                 }
             }
         }
-        state.into_table()
+        Ok(state.into_table())
     }
 }
 
@@ -49,6 +76,18 @@ impl<'a> FromIterator<Line<'a>> for Lines<'a> {
 pub struct Line<'a> {
     pub data: Option<Data<'a>>,
     pub meta: Option<Comment>,
+    pub spans: Spans,
+}
+
+/// Spans of the line parts in the input.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Spans {
+    /// The start of the line.
+    pub start: usize,
+    pub data: Option<Span>,
+    /// The key and the value spans of a key-value line.
+    pub key_value: Option<(Span, Span)>,
+    pub comment: Option<Span>,
 }
 
 /// Data.
@@ -90,7 +129,7 @@ impl<'a> State<'a> {
                     item = Item::from(Value::from(vec![item]));
                 }
                 let value = Value::wrap(key.into_inner(), item);
-                outer_table.merge(value);
+                outer_table.merge(value).expect("validated header");
                 outer_table
             }
         }
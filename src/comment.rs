@@ -1,5 +1,6 @@
 use derive_more::{Deref, DerefMut, From, Into};
 use derive_new::new;
+use serde::{Deserialize, Serialize};
 use std::{
     borrow::Borrow,
     fmt::{self, Display, Formatter},
@@ -18,7 +19,9 @@ impl Display for Comment {
 }
 
 /// Comments.
-#[derive(Clone, Debug, Default, Deref, DerefMut, From, Into, PartialEq, new)]
+#[derive(
+    Clone, Debug, Default, Deref, DerefMut, Deserialize, From, Into, PartialEq, Serialize, new,
+)]
 pub struct Comments(#[new(default)] Vec<Comment>);
 
 impl Comments {
@@ -52,7 +55,7 @@ impl Comments {
 }
 
 /// Kind.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum Kind<T> {
     Pre(T),
     Post(T),
@@ -0,0 +1,427 @@
+use chrono::{
+    DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone, Timelike,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+
+/// Date time.
+///
+/// Remembers the spelling of the original value, so that it is displayed
+/// back the same way.
+#[derive(Clone, Copy, Debug)]
+pub enum Datetime {
+    OffsetDateTime(DateTime<FixedOffset>, Format),
+    LocalDateTime(NaiveDateTime, Format),
+    LocalDate(NaiveDate),
+    LocalTime(NaiveTime, Format),
+}
+
+impl Datetime {
+    /// Extracts the date if there is one.
+    pub fn date(&self) -> Option<NaiveDate> {
+        match self {
+            Self::OffsetDateTime(date_time, _) => Some(date_time.naive_local().date()),
+            Self::LocalDateTime(date_time, _) => Some(date_time.date()),
+            Self::LocalDate(date) => Some(*date),
+            Self::LocalTime(..) => None,
+        }
+    }
+
+    /// Extracts the time if there is one.
+    pub fn time(&self) -> Option<NaiveTime> {
+        match self {
+            Self::OffsetDateTime(date_time, _) => Some(date_time.naive_local().time()),
+            Self::LocalDateTime(date_time, _) => Some(date_time.time()),
+            Self::LocalDate(_) => None,
+            Self::LocalTime(time, _) => Some(*time),
+        }
+    }
+
+    /// Extracts the offset if there is one.
+    pub fn offset(&self) -> Option<FixedOffset> {
+        match self {
+            Self::OffsetDateTime(date_time, _) => Some(date_time.offset().fix()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Datetime {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let format = match self {
+            Self::OffsetDateTime(_, format)
+            | Self::LocalDateTime(_, format)
+            | Self::LocalTime(_, format) => *format,
+            Self::LocalDate(_) => Format::default(),
+        };
+        if let Some(date) = self.date() {
+            write!(f, "{}", date.format("%Y-%m-%d"))?;
+            if self.time().is_some() {
+                write!(f, "{}", format.separator)?;
+            }
+        }
+        if let Some(time) = self.time() {
+            write!(f, "{}", time.format("%H:%M:%S"))?;
+            if let Some(precision) = format.precision {
+                let fraction = time.nanosecond() % 1_000_000_000 / 10u32.pow(9 - precision);
+                write!(
+                    f,
+                    ".{:0precision$}",
+                    fraction,
+                    precision = precision as usize
+                )?;
+            }
+        }
+        if let Some(offset) = self.offset() {
+            match format.zulu {
+                Some(zulu) if offset.local_minus_utc() == 0 => write!(f, "{}", zulu)?,
+                _ if format.unknown_offset && offset.local_minus_utc() == 0 => {
+                    f.write_str("-00:00")?
+                }
+                _ => write!(f, "{}", offset)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Datetime {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut format = Format::default();
+        let (date, rest) = if s.as_bytes().get(4) == Some(&b'-') {
+            let date = parse_date(s.get(..10).ok_or(Error)?)?;
+            let rest = &s[10..];
+            if rest.is_empty() {
+                return Ok(Self::LocalDate(date));
+            }
+            format.separator = match rest.as_bytes()[0] {
+                separator @ b'T' | separator @ b't' | separator @ b' ' => separator as char,
+                _ => return Err(Error),
+            };
+            (Some(date), &rest[1..])
+        } else {
+            (None, s)
+        };
+        let (time, precision, rest) = parse_time(rest)?;
+        format.precision = precision;
+        let date = match date {
+            Some(date) => date,
+            None if rest.is_empty() => return Ok(Self::LocalTime(time, format)),
+            None => return Err(Error),
+        };
+        let date_time = date.and_time(time);
+        let offset = match rest {
+            "" => return Ok(Self::LocalDateTime(date_time, format)),
+            "Z" | "z" => {
+                format.zulu = rest.chars().next();
+                0
+            }
+            _ => {
+                format.zulu = None;
+                format.unknown_offset = rest == "-00:00";
+                parse_offset(rest)?
+            }
+        };
+        let date_time = FixedOffset::east_opt(offset)
+            .and_then(|offset| offset.from_local_datetime(&date_time).single())
+            .ok_or(Error)?;
+        Ok(Self::OffsetDateTime(date_time, format))
+    }
+}
+
+/// The newtype name a date time is serialized with, so that the serializer
+/// of the crate tells it from a string.
+pub(crate) const SERDE_NAME: &str = "$__toml_lalrpop_private_datetime";
+
+impl Serialize for Datetime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(SERDE_NAME, &self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Datetime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let date_time = String::deserialize(deserializer)?;
+        date_time.parse().map_err(de::Error::custom)
+    }
+}
+
+impl From<DateTime<FixedOffset>> for Datetime {
+    #[inline]
+    fn from(from: DateTime<FixedOffset>) -> Self {
+        Self::OffsetDateTime(from, Format::new(from.nanosecond()))
+    }
+}
+
+impl From<NaiveDateTime> for Datetime {
+    #[inline]
+    fn from(from: NaiveDateTime) -> Self {
+        Self::LocalDateTime(from, Format::new(from.nanosecond()))
+    }
+}
+
+impl From<NaiveDate> for Datetime {
+    #[inline]
+    fn from(from: NaiveDate) -> Self {
+        Self::LocalDate(from)
+    }
+}
+
+impl From<NaiveTime> for Datetime {
+    #[inline]
+    fn from(from: NaiveTime) -> Self {
+        Self::LocalTime(from, Format::new(from.nanosecond()))
+    }
+}
+
+/// Date times are equal when they have the same date, time and offset,
+/// whatever their format. Offset date times at the same instant with
+/// different offsets are not equal.
+impl PartialEq for Datetime {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Datetime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::OffsetDateTime(a, _), Self::OffsetDateTime(b, _)) => {
+                Some(a.cmp(b).then_with(|| {
+                    a.offset()
+                        .local_minus_utc()
+                        .cmp(&b.offset().local_minus_utc())
+                }))
+            }
+            (Self::LocalDateTime(a, _), Self::LocalDateTime(b, _)) => a.partial_cmp(b),
+            (Self::LocalDate(a), Self::LocalDate(b)) => a.partial_cmp(b),
+            (Self::LocalTime(a, _), Self::LocalTime(b, _)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+/// Date time format.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Format {
+    /// The date and time separator: `T`, `t` or space.
+    pub separator: char,
+    /// The UTC designator (`Z` or `z`), if it was used instead of a numeric
+    /// offset.
+    pub zulu: Option<char>,
+    /// Whether a zero offset is written `-00:00`, the unknown local offset.
+    pub unknown_offset: bool,
+    /// The number of fractional second digits, at most nine.
+    pub precision: Option<u32>,
+}
+
+impl Format {
+    /// Returns the default format with the shortest precision representing
+    /// the nanoseconds.
+    pub fn new(nanosecond: u32) -> Self {
+        let mut nanosecond = nanosecond % 1_000_000_000;
+        let precision = if nanosecond == 0 {
+            None
+        } else {
+            let mut precision = 9;
+            // `u32::is_multiple_of` needs Rust 1.87.
+            #[allow(clippy::manual_is_multiple_of)]
+            while nanosecond % 10 == 0 {
+                nanosecond /= 10;
+                precision -= 1;
+            }
+            Some(precision)
+        };
+        Self {
+            precision,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Self {
+            separator: 'T',
+            zulu: Some('Z'),
+            unknown_offset: false,
+            precision: None,
+        }
+    }
+}
+
+/// Date time error.
+#[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
+#[error("invalid date time")]
+pub struct Error;
+
+fn parse_date(s: &str) -> Result<NaiveDate, Error> {
+    let bytes = s.as_bytes();
+    if bytes[4] != b'-' || bytes[7] != b'-' {
+        return Err(Error);
+    }
+    let year = parse_digits(&s[..4])?;
+    let month = parse_digits(&s[5..7])?;
+    let day = parse_digits(&s[8..10])?;
+    NaiveDate::from_ymd_opt(year as i32, month, day).ok_or(Error)
+}
+
+/// Parses `HH:MM:SS(.fraction)?`, returns the time, the precision and the
+/// rest of the input.
+fn parse_time(s: &str) -> Result<(NaiveTime, Option<u32>, &str), Error> {
+    let bytes = s.as_bytes();
+    if !s.is_char_boundary(8) || bytes[2] != b':' || bytes[5] != b':' {
+        return Err(Error);
+    }
+    let hour = parse_digits(&s[..2])?;
+    let minute = parse_digits(&s[3..5])?;
+    let second = parse_digits(&s[6..8])?;
+    let mut rest = &s[8..];
+    let mut precision = None;
+    let mut nanosecond = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(fraction.len());
+        if digits == 0 {
+            return Err(Error);
+        }
+        // Additional precision is truncated.
+        let truncated = digits.min(9);
+        nanosecond = parse_digits(&fraction[..truncated])? * 10u32.pow(9 - truncated as u32);
+        precision = Some(truncated as u32);
+        rest = &fraction[digits..];
+    }
+    // A leap second is the last second extended past a billion nanoseconds.
+    let (second, nanosecond) = match second {
+        60 => (59, nanosecond + 1_000_000_000),
+        second => (second, nanosecond),
+    };
+    let time = NaiveTime::from_hms_nano_opt(hour, minute, second, nanosecond).ok_or(Error)?;
+    Ok((time, precision, rest))
+}
+
+/// Parses `[+-]HH:MM` into seconds east of UTC.
+fn parse_offset(s: &str) -> Result<i32, Error> {
+    let bytes = s.as_bytes();
+    if bytes.len() != 6 || bytes[3] != b':' {
+        return Err(Error);
+    }
+    let sign = match bytes[0] {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return Err(Error),
+    };
+    let hours = parse_digits(&s[1..3])?;
+    let minutes = parse_digits(&s[4..6])?;
+    if hours > 23 || minutes > 59 {
+        return Err(Error);
+    }
+    Ok(sign * (hours * 3600 + minutes * 60) as i32)
+}
+
+fn parse_digits(s: &str) -> Result<u32, Error> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(Error);
+    }
+    s.parse().map_err(|_| Error)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(input: &str) -> String {
+        input.parse::<Datetime>().unwrap().to_string()
+    }
+
+    #[test]
+    fn offset_date_time() {
+        assert_eq!(round_trip("1979-05-27T07:32:00Z"), "1979-05-27T07:32:00Z");
+        assert_eq!(round_trip("1979-05-27t07:32:00z"), "1979-05-27t07:32:00z");
+        assert_eq!(
+            round_trip("1979-05-27T00:32:00-07:00"),
+            "1979-05-27T00:32:00-07:00"
+        );
+        assert_eq!(
+            round_trip("1979-05-27 00:32:00.999999-07:00"),
+            "1979-05-27 00:32:00.999999-07:00"
+        );
+        assert_eq!(
+            round_trip("1979-05-27T00:32:00.500+00:00"),
+            "1979-05-27T00:32:00.500+00:00"
+        );
+        assert_eq!(
+            round_trip("1979-05-27T00:32:00-00:00"),
+            "1979-05-27T00:32:00-00:00"
+        );
+        let a = "1979-05-27T07:32:00Z".parse::<Datetime>().unwrap();
+        let b = "1979-05-27T00:32:00-07:00".parse::<Datetime>().unwrap();
+        assert_ne!(a, b);
+        assert_eq!(a.partial_cmp(&b), Some(Ordering::Greater));
+        assert_eq!(a, "1979-05-27T07:32:00+00:00".parse().unwrap());
+    }
+
+    #[test]
+    fn leap_second() {
+        assert_eq!(round_trip("1990-12-31T23:59:60Z"), "1990-12-31T23:59:60Z");
+        assert_eq!(
+            round_trip("1990-12-31T15:59:60.25-08:00"),
+            "1990-12-31T15:59:60.25-08:00"
+        );
+        assert_eq!(round_trip("23:59:60"), "23:59:60");
+        assert_eq!("23:59:61".parse::<Datetime>(), Err(Error));
+    }
+
+    #[test]
+    fn local_date_time() {
+        let date_time = "1979-05-27 07:32:00".parse::<Datetime>().unwrap();
+        assert!(matches!(date_time, Datetime::LocalDateTime(..)));
+        assert_eq!(date_time.to_string(), "1979-05-27 07:32:00");
+        assert_eq!(
+            round_trip("1979-05-27T00:32:00.999999"),
+            "1979-05-27T00:32:00.999999"
+        );
+    }
+
+    #[test]
+    fn local_date() {
+        let date = "1979-05-27".parse::<Datetime>().unwrap();
+        assert_eq!(
+            date,
+            Datetime::LocalDate(NaiveDate::from_ymd_opt(1979, 5, 27).unwrap())
+        );
+        assert_eq!(date.to_string(), "1979-05-27");
+    }
+
+    #[test]
+    fn local_time() {
+        let time = "07:32:00".parse::<Datetime>().unwrap();
+        assert!(matches!(time, Datetime::LocalTime(..)));
+        assert_eq!(time.to_string(), "07:32:00");
+        assert_eq!(round_trip("00:32:00.999999"), "00:32:00.999999");
+        assert_eq!(round_trip("00:32:00.1234567891"), "00:32:00.123456789");
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!("1979-13-27".parse::<Datetime>(), Err(Error));
+        assert_eq!("1979-05-27X07:32:00".parse::<Datetime>(), Err(Error));
+        assert_eq!("07:32:00Z".parse::<Datetime>(), Err(Error));
+        assert_eq!("1979-05-27T07:32:00+25:00".parse::<Datetime>(), Err(Error));
+        assert_eq!("1979-05-27T07:32:00.".parse::<Datetime>(), Err(Error));
+    }
+
+    #[test]
+    fn default_format() {
+        let time = NaiveTime::from_hms_nano_opt(7, 32, 0, 500_000_000).unwrap();
+        assert_eq!(Datetime::from(time).to_string(), "07:32:00.5");
+    }
+}
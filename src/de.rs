@@ -0,0 +1,513 @@
+//! Deserialization of tables and values.
+//!
+//! The key of an error counts array indices as segments, e.g. `servers.0.ip`.
+
+use crate::{
+    ast::{Data, Kind, Lines},
+    error::{Error, ErrorKind, Result},
+    escape::Span,
+    parser::LinesParser,
+    value::{Array, Item, Primitive, Table, Value},
+};
+use serde::de::{
+    self, value::StrDeserializer, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer,
+    MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use std::{collections::HashMap, convert::TryFrom, fmt::Display, iter::Enumerate, vec};
+
+/// Deserializes the input into a value of the type.
+///
+/// Errors of mismatched values are located at the nearest value or header
+/// of the key in the input.
+pub fn from_str<T: DeserializeOwned>(input: &str) -> Result<T> {
+    let lines = LinesParser::new()
+        .parse(input)
+        .map_err(|error| Error::from_parse_error(input, error))?;
+    let spans = spans(&lines);
+    let table = Table::try_from(lines).map_err(|mut error| {
+        error.locate(input);
+        error
+    })?;
+    T::deserialize(table).map_err(|mut error| {
+        if let ErrorKind::Deserialize(key, _) = &error.kind {
+            let span = (0..=key.len())
+                .rev()
+                .find_map(|len| spans.get(&key[..len]))
+                .copied();
+            if let Some(span) = span {
+                error.span = span;
+                error.locate(input);
+            }
+        }
+        error
+    })
+}
+
+/// Returns the spans of the values and headers by their keys.
+fn spans(lines: &Lines) -> HashMap<Vec<String>, Span> {
+    let mut spans = HashMap::new();
+    let mut arrays_of_tables = HashMap::<Vec<String>, usize>::new();
+    let mut table = Vec::new();
+    for line in &lines.0 {
+        match &line.data {
+            Some(Data::Header(header)) => {
+                let (key, is_array_of_tables) = match header {
+                    Kind::ArrayOfTables(key) => (key, true),
+                    Kind::Table(key) => (key, false),
+                };
+                table.clear();
+                for (index, segment) in key.iter().enumerate() {
+                    table.push(String::from(&**segment));
+                    if is_array_of_tables && index + 1 == key.len() {
+                        *arrays_of_tables.entry(table.clone()).or_default() += 1;
+                    }
+                    if let Some(count) = arrays_of_tables.get(&table) {
+                        table.push((count - 1).to_string());
+                    }
+                }
+                if let Some(span) = line.spans.data {
+                    spans.insert(table.clone(), span);
+                }
+            }
+            Some(Data::KeyValue { key, .. }) => {
+                let key = key.iter().fold(table.clone(), |mut key, segment| {
+                    key.push(String::from(&**segment));
+                    key
+                });
+                if let Some((_, span)) = line.spans.key_value {
+                    spans.insert(key, span);
+                }
+            }
+            None => {}
+        }
+    }
+    spans
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::new(
+            ErrorKind::Deserialize(Vec::new(), msg.to_string()),
+            Span::new(),
+        )
+    }
+}
+
+impl Error {
+    /// Prepends the segment to the key of the deserialization error.
+    fn prepend(mut self, segment: String) -> Self {
+        if let ErrorKind::Deserialize(key, _) = &mut self.kind {
+            key.insert(0, segment);
+        }
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Value::Primitive(Primitive::String(string)) => {
+                visitor.visit_string(string.as_str().to_owned())
+            }
+            Value::Primitive(Primitive::Integer(integer)) => visitor.visit_i64(integer.into()),
+            Value::Primitive(Primitive::Float(float, _)) => visitor.visit_f64(float.into()),
+            Value::Primitive(Primitive::Boolean(boolean)) => visitor.visit_bool(boolean),
+            Value::Primitive(Primitive::DateTime(date_time)) => {
+                visitor.visit_string(date_time.to_string())
+            }
+            Value::Array(array) => array.deserialize_any(visitor),
+            Value::Table(table) => table.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Value::Primitive(Primitive::String(string)) => {
+                let variant: StrDeserializer<Error> = string.as_str().into_deserializer();
+                visitor.visit_enum(variant)
+            }
+            Value::Table(table) => table.deserialize_enum(name, variants, visitor),
+            value => Err(de::Error::invalid_type(value.unexpected(), &"enum")),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Table {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(MapDeserializer::new(self))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let mut iter = self.into_iter();
+        match (iter.next(), iter.next()) {
+            (Some((variant, item)), None) => visitor.visit_enum(EnumDeserializer {
+                variant,
+                value: item.value,
+            }),
+            _ => Err(de::Error::invalid_value(
+                de::Unexpected::Map,
+                &"a table with a single key",
+            )),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Array {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(SeqDeserializer {
+            iter: self.into_iter().enumerate(),
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Table {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl Value {
+    fn unexpected(&self) -> de::Unexpected<'_> {
+        match self {
+            Value::Primitive(Primitive::String(string)) => de::Unexpected::Str(string.as_str()),
+            Value::Primitive(Primitive::Integer(integer)) => {
+                de::Unexpected::Signed((*integer).into())
+            }
+            Value::Primitive(Primitive::Float(float, _)) => de::Unexpected::Float(float.into()),
+            Value::Primitive(Primitive::Boolean(boolean)) => de::Unexpected::Bool(*boolean),
+            Value::Primitive(Primitive::DateTime(_)) => de::Unexpected::Other("datetime"),
+            Value::Array(_) => de::Unexpected::Seq,
+            Value::Table(_) => de::Unexpected::Map,
+        }
+    }
+}
+
+/// Map deserializer.
+struct MapDeserializer {
+    iter: indexmap::map::IntoIter<String, Item>,
+    value: Option<(String, Value)>,
+}
+
+impl MapDeserializer {
+    fn new(table: Table) -> Self {
+        Self {
+            iter: table.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.iter.next() {
+            Some((key, item)) => {
+                let deserializer: StrDeserializer<Error> = key.as_str().into_deserializer();
+                let key_value = seed
+                    .deserialize(deserializer)
+                    .map_err(|e| e.prepend(key.clone()));
+                self.value = Some((key, item.value));
+                key_value.map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let (key, value) = self.value.take().expect("value after key");
+        seed.deserialize(value).map_err(|error| error.prepend(key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Sequence deserializer.
+struct SeqDeserializer {
+    iter: Enumerate<vec::IntoIter<Item>>,
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.iter.next() {
+            Some((index, item)) => seed
+                .deserialize(item.value)
+                .map(Some)
+                .map_err(|error| error.prepend(index.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Enum deserializer of a table with a single key.
+struct EnumDeserializer {
+    variant: String,
+    value: Value,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let deserializer: StrDeserializer<Error> = self.variant.as_str().into_deserializer();
+        let variant = seed.deserialize(deserializer)?;
+        let value = VariantDeserializer {
+            variant: self.variant,
+            value: self.value,
+        };
+        Ok((variant, value))
+    }
+}
+
+/// Variant deserializer.
+struct VariantDeserializer {
+    variant: String,
+    value: Value,
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        let error: Error = de::Error::invalid_type(self.value.unexpected(), &"unit variant");
+        Err(error.prepend(self.variant))
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        let variant = self.variant;
+        seed.deserialize(self.value)
+            .map_err(|error| error.prepend(variant))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        let variant = self.variant;
+        de::Deserializer::deserialize_seq(self.value, visitor)
+            .map_err(|error| error.prepend(variant))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let variant = self.variant;
+        de::Deserializer::deserialize_map(self.value, visitor)
+            .map_err(|error| error.prepend(variant))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::datetime::Datetime;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        title: String,
+        version: Version,
+        ratio: f64,
+        enabled: bool,
+        released: Datetime,
+        mode: Mode,
+        level: Option<u8>,
+        missing: Option<u8>,
+        owner: Owner,
+        servers: Vec<Server>,
+        shape: Shape,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Version(u32);
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Debug,
+        Release,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Shape {
+        Circle(f64),
+        Rectangle { width: f64, height: f64 },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Owner {
+        name: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Server {
+        ip: String,
+        ports: Vec<u16>,
+    }
+
+    const INPUT: &str = r#"# Config.
+title = 'TOML'
+version = 0x10
+ratio = 1_000.5e-3
+enabled = true
+released = 1979-05-27T07:32:00Z
+mode = "release"
+level = 3
+owner.name = "Tom"
+shape = { Rectangle = { width = 1.0, height = 2.0 } }
+
+[[servers]]
+ip = "10.0.0.1"
+ports = [8000, 8001]
+
+[[servers]]
+ip = "10.0.0.2"
+ports = [8002]
+"#;
+
+    #[test]
+    fn deserialize() {
+        let config = from_str::<Config>(INPUT).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                title: "TOML".to_owned(),
+                version: Version(16),
+                ratio: 1.0005,
+                enabled: true,
+                released: "1979-05-27T07:32:00Z".parse().unwrap(),
+                mode: Mode::Release,
+                level: Some(3),
+                missing: None,
+                owner: Owner {
+                    name: "Tom".to_owned(),
+                },
+                servers: vec![
+                    Server {
+                        ip: "10.0.0.1".to_owned(),
+                        ports: vec![8000, 8001],
+                    },
+                    Server {
+                        ip: "10.0.0.2".to_owned(),
+                        ports: vec![8002],
+                    },
+                ],
+                shape: Shape::Rectangle {
+                    width: 1.0,
+                    height: 2.0,
+                },
+            },
+        );
+        let shape = from_str::<HashMap<String, Shape>>("a = { Circle = 1.5 }").unwrap();
+        assert_eq!(shape["a"], Shape::Circle(1.5));
+    }
+
+    #[test]
+    fn table() {
+        let table = crate::parse(INPUT).unwrap();
+        let config = Config::deserialize(table).unwrap();
+        assert_eq!(config.servers[1].ports, vec![8002]);
+    }
+
+    #[test]
+    fn error() {
+        let input = INPUT.replace("ports = [8002]", "ports = [8002, 70000]");
+        let error = from_str::<Config>(&input).unwrap_err();
+        match &error.kind {
+            ErrorKind::Deserialize(key, _) => assert_eq!(key, &["servers", "1", "ports", "1"]),
+            kind => panic!("{:?}", kind),
+        }
+        assert_eq!((error.line, error.column), (18, 9));
+        assert!(error
+            .to_string()
+            .ends_with(" for key `servers.1.ports.1` at line 18, column 9"));
+
+        let input = INPUT.replace("mode = \"release\"", "mode = \"fast\"");
+        let error = from_str::<Config>(&input).unwrap_err();
+        assert_eq!((error.line, error.column), (7, 8));
+
+        let error = from_str::<Owner>("[owner]").unwrap_err();
+        assert_eq!(error.to_string(), "missing field `name`");
+        let error = Owner::deserialize(crate::parse("name = 1").unwrap()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid type: integer `1`, expected a string for key `name`",
+        );
+    }
+}
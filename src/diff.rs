@@ -0,0 +1,294 @@
+use crate::{
+    comment::Comments,
+    format::Inline,
+    key::Segment,
+    path::{Path, Step},
+    value::{Array, Item, Primitive, Table, Value},
+};
+use derive_more::{Deref, IntoIterator};
+use std::fmt::{self, Display, Formatter};
+
+/// Returns the changes from the old table to the new one.
+pub fn diff(old: &Table, new: &Table) -> Diff {
+    let mut diff = Diff::default();
+    diff.tables(old, new, &mut Vec::new());
+    diff
+}
+
+/// Diff.
+///
+/// Displays as a unified diff, a line per added or removed value.
+#[derive(Clone, Debug, Default, Deref, IntoIterator, PartialEq)]
+pub struct Diff(Vec<Change>);
+
+impl Diff {
+    fn push(&mut self, path: &[Step<'static>], kind: ChangeKind) {
+        self.0.push(Change {
+            path: Path(path.to_vec()),
+            kind,
+        });
+    }
+
+    fn tables(&mut self, old: &Table, new: &Table, path: &mut Vec<Step<'static>>) {
+        for (key, old) in old.iter() {
+            path.push(Step::Key(Segment::new(key.clone())));
+            match new.get(key) {
+                Some(new) => self.items(old, new, path),
+                None => self.push(path, ChangeKind::Removed(old.value.clone())),
+            }
+            path.pop();
+        }
+        for (key, new) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+            path.push(Step::Key(Segment::new(key.clone())));
+            self.push(path, ChangeKind::Added(new.value.clone()));
+            path.pop();
+        }
+    }
+
+    fn items(&mut self, old: &Item, new: &Item, path: &mut Vec<Step<'static>>) {
+        if old.comments != new.comments {
+            let kind = ChangeKind::Comments(old.comments.clone(), new.comments.clone());
+            self.push(path, kind);
+        }
+        self.values(&old.value, &new.value, path);
+    }
+
+    fn values(&mut self, old: &Value, new: &Value, path: &mut Vec<Step<'static>>) {
+        match (old, new) {
+            (Value::Table(old), Value::Table(new)) => self.tables(old, new, path),
+            (Value::Array(old), Value::Array(new)) => self.arrays(old, new, path),
+            (Value::Primitive(a), Value::Primitive(b)) if same(a, b) => {
+                if a.to_string() != b.to_string() {
+                    let kind = ChangeKind::Representation(old.clone(), new.clone());
+                    self.push(path, kind);
+                }
+            }
+            _ => self.push(path, ChangeKind::Changed(old.clone(), new.clone())),
+        }
+    }
+
+    /// Matches the equal elements by their longest common subsequence. The
+    /// elements left between two matches are compared pairwise, the rest of
+    /// them are deleted or inserted.
+    ///
+    /// The indices of the paths are the ones at the time the changes are
+    /// applied in order.
+    fn arrays(&mut self, old: &Array, new: &Array, path: &mut Vec<Step<'static>>) {
+        let (n, m) = (old.len(), new.len());
+        let mut lengths = vec![vec![0; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[i][j] = if equal(&old[i].value, &new[j].value) {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        let (mut deleted, mut inserted) = (Vec::new(), Vec::new());
+        while i < n || j < m {
+            if i < n && j < m && equal(&old[i].value, &new[j].value) {
+                self.gap(old, new, &mut deleted, &mut inserted, j, path);
+                path.push(Step::Index(j));
+                self.items(&old[i], &new[j], path);
+                path.pop();
+                i += 1;
+                j += 1;
+            } else if j < m && (i == n || lengths[i][j + 1] >= lengths[i + 1][j]) {
+                inserted.push(j);
+                j += 1;
+            } else {
+                deleted.push(i);
+                i += 1;
+            }
+        }
+        self.gap(old, new, &mut deleted, &mut inserted, j, path);
+    }
+
+    fn gap(
+        &mut self,
+        old: &Array,
+        new: &Array,
+        deleted: &mut Vec<usize>,
+        inserted: &mut Vec<usize>,
+        end: usize,
+        path: &mut Vec<Step<'static>>,
+    ) {
+        // The deleted elements left follow the paired ones.
+        let start = end - inserted.len();
+        let position = start + deleted.len().min(inserted.len());
+        for index in 0..deleted.len().max(inserted.len()) {
+            match (deleted.get(index), inserted.get(index)) {
+                (Some(&i), Some(&j)) => {
+                    path.push(Step::Index(j));
+                    self.items(&old[i], &new[j], path);
+                }
+                (Some(&i), None) => {
+                    path.push(Step::Index(position));
+                    self.push(path, ChangeKind::Deleted(old[i].value.clone()));
+                }
+                (None, Some(&j)) => {
+                    path.push(Step::Index(j));
+                    self.push(path, ChangeKind::Inserted(new[j].value.clone()));
+                }
+                (None, None) => unreachable!(),
+            }
+            path.pop();
+        }
+        deleted.clear();
+        inserted.clear();
+    }
+}
+
+impl Display for Diff {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for change in self.iter() {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Change.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    /// The full path of the changed value.
+    pub path: Path<'static>,
+    pub kind: ChangeKind,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let path = &self.path;
+        match &self.kind {
+            ChangeKind::Added(value) | ChangeKind::Inserted(value) => {
+                write!(f, "+ {} = {}", path, Inline::new(value))
+            }
+            ChangeKind::Removed(value) | ChangeKind::Deleted(value) => {
+                write!(f, "- {} = {}", path, Inline::new(value))
+            }
+            ChangeKind::Changed(old, new) | ChangeKind::Representation(old, new) => {
+                writeln!(f, "- {} = {}", path, Inline::new(old))?;
+                write!(f, "+ {} = {}", path, Inline::new(new))
+            }
+            ChangeKind::Comments(old, new) => {
+                write!(f, "- {}", path)?;
+                for comment in old.iter() {
+                    write!(f, " {}", comment)?;
+                }
+                write!(f, "\n+ {}", path)?;
+                for comment in new.iter() {
+                    write!(f, " {}", comment)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Change kind.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChangeKind {
+    /// The key is added.
+    Added(Value),
+    /// The key is removed.
+    Removed(Value),
+    /// The value is changed.
+    Changed(Value, Value),
+    /// The array element is inserted.
+    Inserted(Value),
+    /// The array element is removed.
+    Deleted(Value),
+    /// The value is the same, but spelled differently.
+    Representation(Value, Value),
+    /// The comments of the value are changed.
+    Comments(Comments, Comments),
+}
+
+/// Tests whether the primitives are the same regardless of their
+/// representation.
+fn same(a: &Primitive, b: &Primitive) -> bool {
+    match (a, b) {
+        (Primitive::String(a), Primitive::String(b)) => a.as_str() == b.as_str(),
+        _ => a == b,
+    }
+}
+
+/// Tests whether the values are the same regardless of their representation
+/// and comments.
+pub(crate) fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Primitive(a), Value::Primitive(b)) => same(a, b),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| equal(a, b))
+        }
+        (Value::Table(a), Value::Table(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| matches!(b.get(key), Some(b) if equal(a, b)))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn changes() {
+        let old = parse(
+            r#"a = 1
+b = 0xff
+c = 'd'
+# e
+f = [1, 2, 3, 4]
+g = { h = 1 }
+i = [{ j = 1 }, { j = 2 }]
+"#,
+        )
+        .unwrap();
+        let new = parse(
+            r#"a = 2
+b = 255
+c = "d"
+# E
+f = [0, 1, 3, 4, 5]
+g = { h = 1, k = 2 }
+i = [{ j = 1 }, { j = 3 }]
+l = true
+"#,
+        )
+        .unwrap();
+        let diff = diff(&old, &new);
+        let kinds = diff
+            .iter()
+            .map(|change| (change.path.to_string(), &change.kind));
+        let kinds: Vec<_> = kinds.collect();
+        assert!(matches!(kinds[0], (ref path, ChangeKind::Changed(..)) if path == "a"));
+        assert!(matches!(kinds[1], (ref path, ChangeKind::Representation(..)) if path == "b"));
+        assert!(matches!(kinds[2], (ref path, ChangeKind::Representation(..)) if path == "c"));
+        assert!(matches!(kinds[3], (ref path, ChangeKind::Comments(..)) if path == "f"));
+        assert!(matches!(kinds[4], (ref path, ChangeKind::Inserted(..)) if path == "f[0]"));
+        assert!(matches!(kinds[5], (ref path, ChangeKind::Deleted(..)) if path == "f[2]"));
+        assert!(matches!(kinds[6], (ref path, ChangeKind::Inserted(..)) if path == "f[4]"));
+        assert!(matches!(kinds[7], (ref path, ChangeKind::Added(..)) if path == "g.k"));
+        assert!(matches!(kinds[8], (ref path, ChangeKind::Changed(..)) if path == "i[1].j"));
+        assert!(matches!(kinds[9], (ref path, ChangeKind::Added(..)) if path == "l"));
+        assert_eq!(kinds.len(), 10);
+        assert_eq!(
+            diff.to_string(),
+            "- a = 1\n+ a = 2\n- b = 0xff\n+ b = 255\n- c = 'd'\n+ c = \"d\"\n\
+             - f # e\n+ f # E\n+ f[0] = 0\n- f[2] = 2\n+ f[4] = 5\n+ g.k = 2\n\
+             - i[1].j = 2\n+ i[1].j = 3\n+ l = true\n",
+        );
+    }
+
+    #[test]
+    fn same_tables() {
+        let input = "a = [1, { b = 'c' }]\n[d]\ne = 1.0\n";
+        assert!(diff(&parse(input).unwrap(), &parse(input).unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,381 @@
+use crate::{
+    ast::{self, Lines, Spans},
+    comment::Comment,
+    error::{Error, Result},
+    escape::Span,
+    format::{independent::Kind, Inline, LineEnding},
+    key::Key,
+    parser::LinesParser,
+    value::{Table, Value},
+};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+    iter::once,
+    mem::replace,
+    str::FromStr,
+};
+
+/// Document.
+///
+/// A lossless syntax layer beneath the table: every line keeps its
+/// whitespace, so that an unmodified document is displayed back byte for
+/// byte.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Document {
+    pub lines: Vec<Line>,
+    /// The line ending detected from the input, that of its first line.
+    pub line_ending: LineEnding,
+}
+
+impl Document {
+    fn new(input: &str, lines: Lines) -> Self {
+        let line_ending = LineEnding::detect(input);
+        // Each line ends where the line feed before the next line starts.
+        let ends = lines
+            .0
+            .iter()
+            .skip(1)
+            .map(|line| {
+                let line_feed = line.spans.start - 1;
+                if input[..line_feed].ends_with('\r') {
+                    (line_feed - 1, LineEnding::CrLf)
+                } else {
+                    (line_feed, LineEnding::Lf)
+                }
+            })
+            .chain(once((input.len(), line_ending)))
+            .collect::<Vec<_>>();
+        let lines = lines
+            .0
+            .into_iter()
+            .zip(ends)
+            .map(|(line, (end, line_ending))| Line::new(input, line, end, line_ending))
+            .collect();
+        Self { lines, line_ending }
+    }
+
+    /// Returns the table of the document.
+    ///
+    /// Fails if the lines, once edited, define a key more than once.
+    pub fn table(&self) -> Result<Table> {
+        let mut start = 0;
+        let lines = self
+            .lines
+            .iter()
+            .map(|line| {
+                let mut spans = Spans {
+                    start,
+                    ..Spans::default()
+                };
+                start += line.to_string().len() + line.line_ending.as_str().len();
+                let data_start = spans.start + line.indent.len();
+                let data = line.data.as_ref().map(|data| match data {
+                    Data::Header { key, raw } => {
+                        spans.data = Some(Span::from(data_start..data_start + raw.len()));
+                        ast::Data::Header(key.clone())
+                    }
+                    Data::KeyValue {
+                        key,
+                        raw_key,
+                        value,
+                        ..
+                    } => {
+                        let key_span = Span::from(data_start..data_start + raw_key.len());
+                        spans.key_value = Some((key_span, Span::new()));
+                        ast::Data::KeyValue {
+                            key: key.clone(),
+                            value: value.clone(),
+                        }
+                    }
+                });
+                let meta = line.comment.as_ref().map(|comment| {
+                    let comment = comment.trim_start_matches('#').trim_end().to_owned();
+                    if data.is_none() {
+                        Comment::Pre(comment)
+                    } else {
+                        Comment::Post(comment)
+                    }
+                });
+                ast::Line { data, meta, spans }
+            })
+            .collect::<Lines>();
+        Table::try_from(lines).map_err(|mut error| {
+            error.locate(&self.to_string());
+            error
+        })
+    }
+
+    /// Sets the value of the first key-value line with the key, keeping the
+    /// whitespace of the line.
+    ///
+    /// The key is the full key: the header key followed by the line key.
+    /// Returns the previous value, or `None` if there is no such line.
+    pub fn set(&mut self, key: &[&str], value: Value) -> Option<Value> {
+        let mut header: &[_] = &[];
+        let index = self.lines.iter().position(|line| match &line.data {
+            Some(Data::Header { key, .. }) => {
+                header = match key {
+                    Kind::ArrayOfTables(key) | Kind::Table(key) => key,
+                };
+                false
+            }
+            Some(Data::KeyValue { key: line_key, .. }) => header
+                .iter()
+                .chain(line_key.iter())
+                .map(|segment| &**segment)
+                .eq(key.iter().copied()),
+            None => false,
+        })?;
+        match &mut self.lines[index].data {
+            Some(Data::KeyValue {
+                value: target,
+                raw_value,
+                ..
+            }) => {
+                *raw_value = None;
+                Some(replace(target, value))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (index, line) in self.lines.iter().enumerate() {
+            if index != 0 {
+                Display::fmt(&self.lines[index - 1].line_ending, f)?;
+            }
+            Display::fmt(line, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let lines = LinesParser::new()
+            .parse(s)
+            .map_err(|error| Error::from_parse_error(s, error))?;
+        lines.validate().map_err(|mut error| {
+            error.locate(s);
+            error
+        })?;
+        Ok(Self::new(s, lines))
+    }
+}
+
+impl TryFrom<&Document> for Table {
+    type Error = Error;
+
+    fn try_from(from: &Document) -> Result<Self> {
+        from.table()
+    }
+}
+
+/// Document line.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Line {
+    /// The whitespace before the data.
+    pub indent: String,
+    pub data: Option<Data>,
+    /// The whitespace between the data and the comment.
+    pub trailing: String,
+    /// The raw comment, including `#`.
+    pub comment: Option<String>,
+    /// The line ending after the line, that of the document for the last
+    /// line.
+    pub line_ending: LineEnding,
+}
+
+impl Line {
+    fn new(input: &str, line: ast::Line, end: usize, line_ending: LineEnding) -> Self {
+        let ast::Line { data, spans, .. } = line;
+        let comment_start = spans.comment.map_or(end, |span| span.start);
+        let (data_start, data_end) = spans.data.map_or((comment_start, comment_start), |span| {
+            (span.start, span.end)
+        });
+        let data = data.map(|data| match data {
+            ast::Data::Header(key) => Data::Header {
+                key: match key {
+                    Kind::ArrayOfTables(key) => Kind::ArrayOfTables(key.into_owned()),
+                    Kind::Table(key) => Kind::Table(key.into_owned()),
+                },
+                raw: input[data_start..data_end].to_owned(),
+            },
+            ast::Data::KeyValue { key, value } => {
+                let (key_span, value_span) = spans.key_value.expect("key-value spans");
+                let separator = &input[key_span.end..value_span.start];
+                let equals = separator.find('=').expect("key-value separator");
+                Data::KeyValue {
+                    key: key.into_owned(),
+                    raw_key: input[key_span.start..key_span.end].to_owned(),
+                    before: separator[..equals].to_owned(),
+                    after: separator[equals + 1..].to_owned(),
+                    value,
+                    raw_value: Some(input[value_span.start..value_span.end].to_owned()),
+                }
+            }
+        });
+        Self {
+            indent: input[spans.start..data_start].to_owned(),
+            data,
+            trailing: input[data_end..comment_start].to_owned(),
+            comment: spans
+                .comment
+                .map(|span| input[span.start..span.end].to_owned()),
+            line_ending,
+        }
+    }
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&self.indent)?;
+        if let Some(data) = &self.data {
+            Display::fmt(data, f)?;
+        }
+        f.write_str(&self.trailing)?;
+        if let Some(comment) = &self.comment {
+            f.write_str(comment)?;
+        }
+        Ok(())
+    }
+}
+
+/// Document line data.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Data {
+    Header {
+        key: Kind<Key<'static>>,
+        /// The raw header, including brackets.
+        raw: String,
+    },
+    KeyValue {
+        key: Key<'static>,
+        raw_key: String,
+        /// The whitespace before `=`.
+        before: String,
+        /// The whitespace after `=`.
+        after: String,
+        value: Value,
+        /// The raw value, `None` once the value is changed.
+        raw_value: Option<String>,
+    },
+}
+
+impl Display for Data {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Header { raw, .. } => f.write_str(raw),
+            Self::KeyValue {
+                raw_key,
+                before,
+                after,
+                value,
+                raw_value,
+                ..
+            } => {
+                write!(f, "{}{}={}", raw_key, before, after)?;
+                match raw_value {
+                    Some(raw_value) => f.write_str(raw_value),
+                    None => Display::fmt(&Inline::new(value), f),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const INPUT: &str = r#"# Header comment.
+
+title   = "TOML"   # Title.
+  owner.name = 'Tom'
+
+[ database ]
+	ports = [ 8000,
+	          8001 ]  # Ports.
+enabled=true
+
+
+[[ servers ]]
+ip  =  "10.0.0.1"
+"#;
+
+    #[test]
+    fn round_trip() {
+        let document = INPUT.parse::<Document>().unwrap();
+        assert_eq!(document.to_string(), INPUT);
+        let document = "a = 1".parse::<Document>().unwrap();
+        assert_eq!(document.to_string(), "a = 1");
+        let document = "\n\n".parse::<Document>().unwrap();
+        assert_eq!(document.to_string(), "\n\n");
+    }
+
+    #[test]
+    fn crlf() {
+        let input = INPUT.replace('\n', "\r\n");
+        let document = input.parse::<Document>().unwrap();
+        assert_eq!(document.line_ending, LineEnding::CrLf);
+        assert_eq!(document.lines[2].comment.as_deref(), Some("# Title."));
+        assert_eq!(document.table().unwrap(), crate::parse(INPUT).unwrap());
+        assert_eq!(document.to_string(), input);
+    }
+
+    #[test]
+    fn mixed_line_endings() {
+        let input = "a = 1\r\n\nb = 2 # c\n\r\n[d]\r\ne = 3";
+        let document = input.parse::<Document>().unwrap();
+        assert_eq!(document.line_ending, LineEnding::CrLf);
+        assert_eq!(document.lines[1].line_ending, LineEnding::Lf);
+        assert_eq!(document.lines[2].comment.as_deref(), Some("# c"));
+        assert_eq!(document.to_string(), input);
+        let table = crate::parse("a = 1\nb = 2 # c\n[d]\ne = 3").unwrap();
+        assert_eq!(document.table().unwrap(), table);
+        let mut document = "[a]\r\n\nb = 1".parse::<Document>().unwrap();
+        document.lines.push(document.lines[0].clone());
+        assert_eq!(document.to_string(), "[a]\r\n\nb = 1\r\n[a]");
+        let error = document.table().unwrap_err();
+        assert_eq!((error.line, error.column), (4, 1));
+    }
+
+    #[test]
+    fn table() {
+        let document = INPUT.parse::<Document>().unwrap();
+        assert_eq!(document.table().unwrap(), crate::parse(INPUT).unwrap());
+    }
+
+    #[test]
+    fn duplicate_key() {
+        let error = "a = 1\n a = 2".parse::<Document>().unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::DuplicateKey(..)));
+        assert_eq!((error.line, error.column), (2, 2));
+        let mut document = "[a]\nb = 1".parse::<Document>().unwrap();
+        document.lines.push(document.lines[0].clone());
+        let error = document.table().unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::DuplicateTable(..)));
+        assert_eq!((error.line, error.column), (3, 1));
+    }
+
+    #[test]
+    fn set() {
+        let mut document = INPUT.parse::<Document>().unwrap();
+        let previous = document.set(&["database", "enabled"], Value::from(false));
+        assert_eq!(previous, Some(Value::from(true)));
+        assert_eq!(document.set(&["enabled"], Value::from(false)), None);
+        document.set(&["title"], Value::from("TOML v1.0".to_owned()));
+        assert_eq!(
+            document.to_string(),
+            INPUT
+                .replace("enabled=true", "enabled=false")
+                .replace(r#""TOML"   "#, r#"'TOML v1.0'   "#),
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use crate::{
+    escape::{self, Span},
+    key::Key,
+};
+use lalrpop_util::ParseError;
+use std::{
+    fmt::{self, Display, Formatter},
+    iter::FromIterator,
+    ops::Range,
+};
+use thiserror::Error;
+
+/// Result.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Error.
+///
+/// Line and column are one-based, the column is counted in chars. Both are
+/// zero if the error is not located in an input.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{kind}{}", Position(*.line, *.column))]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub span: Span,
+    pub line: usize,
+    pub column: usize,
+    pub expected: Vec<String>,
+}
+
+impl Error {
+    pub(crate) fn new<T: Into<Span>>(kind: ErrorKind, span: T) -> Self {
+        Self {
+            kind,
+            span: span.into(),
+            line: 0,
+            column: 0,
+            expected: Vec::new(),
+        }
+    }
+
+    pub(crate) fn from_parse_error<T: Display>(
+        input: &str,
+        error: ParseError<usize, T, Error>,
+    ) -> Self {
+        let mut error = match error {
+            ParseError::InvalidToken { location } => {
+                let end = input[location..]
+                    .chars()
+                    .next()
+                    .map_or(location, |c| location + c.len_utf8());
+                Self::new(ErrorKind::InvalidToken, location..end)
+            }
+            ParseError::UnrecognizedEOF { location, expected } => Self {
+                expected,
+                ..Self::new(ErrorKind::UnrecognizedEof, location..location)
+            },
+            ParseError::UnrecognizedToken {
+                token: (start, token, end),
+                expected,
+            } => Self {
+                expected,
+                ..Self::new(ErrorKind::UnrecognizedToken(token.to_string()), start..end)
+            },
+            ParseError::ExtraToken {
+                token: (start, token, end),
+            } => Self::new(ErrorKind::ExtraToken(token.to_string()), start..end),
+            ParseError::User { error } => error,
+        };
+        error.locate(input);
+        error
+    }
+
+    /// Returns the byte offset of the failing token.
+    pub fn offset(&self) -> usize {
+        self.span.start
+    }
+
+    /// Returns the renderer of the error against the input it was produced
+    /// from.
+    pub fn diagnostic<'a>(&'a self, input: &'a str) -> Diagnostic<'a> {
+        Diagnostic::new(self, input)
+    }
+
+    /// Locates the error, and the previous definition it refers to, in the
+    /// input.
+    pub(crate) fn locate(&mut self, input: &str) {
+        let (line, column) = position(input, self.span.start);
+        self.line = line;
+        self.column = column;
+        if let Some(previous) = self.kind.previous_mut() {
+            let (line, column) = position(input, previous.span.start);
+            previous.line = line;
+            previous.column = column;
+        }
+    }
+}
+
+impl From<escape::Error> for Error {
+    fn from(from: escape::Error) -> Self {
+        Self::new(ErrorKind::Escape(from), from.span())
+    }
+}
+
+/// Error kind.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum ErrorKind {
+    #[error("invalid token")]
+    InvalidToken,
+    #[error("unexpected end of file")]
+    UnrecognizedEof,
+    #[error("unexpected token `{0}`")]
+    UnrecognizedToken(String),
+    #[error("extra token `{0}`")]
+    ExtraToken(String),
+    #[error("invalid boolean")]
+    Boolean,
+    #[error("invalid date time")]
+    DateTime,
+    #[error("invalid float")]
+    Float,
+    #[error("invalid integer")]
+    Integer,
+    #[error("integer out of range")]
+    IntegerOverflow,
+    #[error("{0}")]
+    Escape(escape::Error),
+    #[error("{1}{}", Position::key(.0))]
+    Deserialize(Vec<String>, String),
+    #[error("{0}")]
+    Serialize(String),
+    #[error("duplicate key `{0}` (first defined at {1})")]
+    DuplicateKey(String, Location),
+    #[error("table `{0}` defined more than once (first at {1})")]
+    DuplicateTable(String, Location),
+    #[error("cannot extend value `{0}` (defined at {1})")]
+    ExtendValue(String, Location),
+    #[error("cannot extend table `{0}` with dotted keys (defined at {1})")]
+    ExtendTable(String, Location),
+}
+
+impl ErrorKind {
+    fn previous_mut(&mut self) -> Option<&mut Location> {
+        match self {
+            Self::DuplicateKey(_, previous)
+            | Self::DuplicateTable(_, previous)
+            | Self::ExtendValue(_, previous)
+            | Self::ExtendTable(_, previous) => Some(previous),
+            _ => None,
+        }
+    }
+}
+
+/// Position suffix of the error message.
+struct Position(usize, usize);
+
+impl Position {
+    fn key(key: &[String]) -> String {
+        if key.is_empty() {
+            String::new()
+        } else {
+            format!(" for key `{}`", Key::from_iter(key.iter().cloned()))
+        }
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Self(0, _) => Ok(()),
+            Self(line, column) => write!(f, " at line {}, column {}", line, column),
+        }
+    }
+}
+
+/// Location of a previous definition.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Location {
+    pub span: Span,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    pub(crate) fn new(span: Span) -> Self {
+        Self {
+            span,
+            line: 0,
+            column: 0,
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Diagnostic.
+///
+/// Renders the error with the offending source line and a caret underline.
+#[derive(Clone, Copy, Debug)]
+pub struct Diagnostic<'a> {
+    error: &'a Error,
+    input: &'a str,
+}
+
+impl<'a> Diagnostic<'a> {
+    pub fn new(error: &'a Error, input: &'a str) -> Self {
+        Self { error, input }
+    }
+}
+
+impl Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let Error {
+            kind,
+            span,
+            line: number,
+            column,
+            expected,
+        } = self.error;
+        let start = floor(self.input, span.start);
+        let range = line(self.input, start);
+        let line = &self.input[range.clone()];
+        let width = number.to_string().len();
+        writeln!(f, "error: {}", kind)?;
+        writeln!(f, "{:width$}--> {}:{}", "", number, column, width = width)?;
+        writeln!(f, "{:width$} |", "", width = width)?;
+        writeln!(f, "{} | {}", number, line)?;
+        write!(f, "{:width$} | ", "", width = width)?;
+        for c in line[..start - range.start].chars() {
+            f.write_str(if c == '\t' { "\t" } else { " " })?;
+        }
+        let end = ceil(self.input, span.end.min(range.end)).max(start);
+        let carets = self.input[start..end].chars().count().max(1);
+        writeln!(f, "{}", "^".repeat(carets))?;
+        if !expected.is_empty() {
+            writeln!(
+                f,
+                "{:width$} = expected one of {}",
+                "",
+                expected.join(", "),
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the offset, within the input, moved back to a char boundary.
+fn floor(input: &str, offset: usize) -> usize {
+    let mut offset = offset.min(input.len());
+    while !input.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// Returns the offset, within the input, moved forward to a char boundary.
+fn ceil(input: &str, offset: usize) -> usize {
+    let mut offset = offset.min(input.len());
+    while !input.is_char_boundary(offset) {
+        offset += 1;
+    }
+    offset
+}
+
+/// Returns the one-based line and column of the offset.
+fn position(input: &str, offset: usize) -> (usize, usize) {
+    let range = line(input, offset);
+    let line = input[..range.start].matches('\n').count() + 1;
+    let column = input[range.start..offset].chars().count() + 1;
+    (line, column)
+}
+
+/// Returns the byte range of the line containing the offset, without the line
+/// ending.
+fn line(input: &str, offset: usize) -> Range<usize> {
+    let start = input[..offset].rfind('\n').map_or(0, |index| index + 1);
+    let end = input[offset..]
+        .find('\n')
+        .map_or(input.len(), |index| offset + index);
+    let end = if input[..end].ends_with('\r') && end > offset {
+        end - 1
+    } else {
+        end
+    };
+    start..end
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn escape() {
+        let input = "a = true\nb = \"x\\qy\"";
+        let error = parse(input).unwrap_err();
+        assert_eq!(
+            error.kind,
+            ErrorKind::Escape(escape::Error::InvalidEscape(Span { start: 15, end: 17 }))
+        );
+        assert_eq!(error.offset(), 15);
+        assert_eq!((error.line, error.column), (2, 7));
+        assert_eq!(
+            error.diagnostic(input).to_string(),
+            "error: invalid escape\n --> 2:7\n  |\n2 | b = \"x\\qy\"\n  |       ^^\n",
+        );
+        let input = "a = \"\\é\"";
+        let error = parse(input).unwrap_err();
+        assert_eq!(
+            error.kind,
+            ErrorKind::Escape(escape::Error::InvalidEscape(Span { start: 5, end: 8 }))
+        );
+        assert_eq!(
+            error.diagnostic(input).to_string(),
+            "error: invalid escape\n --> 1:6\n  |\n1 | a = \"\\é\"\n  |      ^^\n",
+        );
+        // A malformed span is clamped to char boundaries.
+        let error = Error {
+            span: Span { start: 7, end: 7 },
+            ..error
+        };
+        assert_eq!(
+            error.diagnostic(input).to_string(),
+            "error: invalid escape\n --> 1:6\n  |\n1 | a = \"\\é\"\n  |       ^\n",
+        );
+    }
+
+    #[test]
+    fn integer_overflow() {
+        let input = "a = 1\nb = 0xffff_ffff_ffff_ffff";
+        let error = parse(input).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::IntegerOverflow);
+        assert_eq!(error.span, Span { start: 10, end: 31 });
+        assert_eq!((error.line, error.column), (2, 5));
+    }
+
+    #[test]
+    fn unrecognized_token() {
+        let input = "a = = 1";
+        let error = parse(input).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnrecognizedToken("=".to_owned()));
+        assert_eq!((error.line, error.column), (1, 5));
+        assert!(!error.expected.is_empty());
+    }
+
+    #[test]
+    fn unrecognized_eof() {
+        let input = "a =";
+        let error = parse(input).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnrecognizedEof);
+        assert_eq!(error.offset(), 3);
+        assert_eq!((error.line, error.column), (1, 4));
+    }
+}
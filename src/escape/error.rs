@@ -22,3 +22,37 @@ pub enum Error {
     #[error("out of range unicode escape")]
     OutOfRangeUnicodeEscape(Span),
 }
+
+impl Error {
+    /// Returns the span of the error.
+    pub fn span(&self) -> Span {
+        match *self {
+            Self::EscapeOnlyChar(span)
+            | Self::IncompleteUnicodeEscape(span)
+            | Self::InvalidCharInUnicodeEscape(span)
+            | Self::InvalidEscape(span)
+            | Self::LoneSlash(span)
+            | Self::SurrogateUnicodeEscape(span)
+            | Self::OutOfRangeUnicodeEscape(span) => span,
+        }
+    }
+
+    /// Shifts the span of the error by the offset.
+    pub fn offset(self, offset: usize) -> Self {
+        match self {
+            Self::EscapeOnlyChar(span) => Self::EscapeOnlyChar(span.offset(offset)),
+            Self::IncompleteUnicodeEscape(span) => {
+                Self::IncompleteUnicodeEscape(span.offset(offset))
+            }
+            Self::InvalidCharInUnicodeEscape(span) => {
+                Self::InvalidCharInUnicodeEscape(span.offset(offset))
+            }
+            Self::InvalidEscape(span) => Self::InvalidEscape(span.offset(offset)),
+            Self::LoneSlash(span) => Self::LoneSlash(span.offset(offset)),
+            Self::SurrogateUnicodeEscape(span) => Self::SurrogateUnicodeEscape(span.offset(offset)),
+            Self::OutOfRangeUnicodeEscape(span) => {
+                Self::OutOfRangeUnicodeEscape(span.offset(offset))
+            }
+        }
+    }
+}
@@ -14,6 +14,7 @@ pub use self::{
     escape::escape,
     unescape::unescape,
 };
+use std::ops::Range;
 
 /// Flags.
 #[derive(Clone, Copy, Debug, Default)]
@@ -57,7 +58,7 @@ pub enum Mode {
 }
 
 /// A span, designating a range of bytes where a char is located.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct Span {
     /// The start of the range.
     pub start: usize,
@@ -67,7 +68,24 @@ pub struct Span {
 
 impl Span {
     pub fn new() -> Span {
-        Self { start: 0, end: 0 }
+        Self::default()
+    }
+
+    /// Shifts the span by the offset.
+    pub fn offset(self, offset: usize) -> Span {
+        Self {
+            start: self.start + offset,
+            end: self.end + offset,
+        }
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(from: Range<usize>) -> Self {
+        Self {
+            start: from.start,
+            end: from.end,
+        }
     }
 }
 
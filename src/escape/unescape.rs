@@ -27,7 +27,7 @@ impl<'a> Unescape<'a> {
             .char_indices
             .next()
             .ok_or(Error::LoneSlash(self.span))?;
-        self.span.end = i + 1;
+        self.span.end = i + c.len_utf8();
         match c {
             't' => Ok((self.span, '\t')),
             'n' => Ok((self.span, '\n')),
@@ -47,7 +47,7 @@ impl<'a> Unescape<'a> {
                 .char_indices
                 .next()
                 .ok_or(Error::IncompleteUnicodeEscape(self.span))?;
-            self.span.end = i + 1;
+            self.span.end = i + c.len_utf8();
             let digit = c
                 .to_digit(16)
                 .ok_or(Error::InvalidCharInUnicodeEscape(self.span))?;
@@ -62,13 +62,15 @@ impl<'a> Unescape<'a> {
         )
     }
 
+    // Advances the original iterator, so that spans stay relative to the
+    // input.
     fn skip_ascii_whitespace(&mut self) {
-        let str = self.char_indices.as_str();
-        let first_non_space = str
-            .bytes()
-            .position(|b| !b.is_ascii_whitespace())
-            .unwrap_or(str.len());
-        self.char_indices = str[first_non_space..].char_indices()
+        while let Some((_, c)) = self.char_indices.clone().next() {
+            if !c.is_ascii_whitespace() {
+                break;
+            }
+            self.char_indices.next();
+        }
     }
 }
 
@@ -80,7 +82,7 @@ impl Iterator for Unescape<'_> {
             let (i, c) = self.char_indices.next()?;
             self.span = Span {
                 start: i,
-                end: i + 1,
+                end: i + c.len_utf8(),
             };
             return match c {
                 '\t' => Some(Ok((self.span, c))),
@@ -93,7 +95,14 @@ impl Iterator for Unescape<'_> {
                     // see [TOML](https://github.com/toml-lang/toml#string).
                     if self.mode == Mode::MultiLine {
                         let mut attempt = self.char_indices.clone();
-                        if let Some((_, '\n')) = attempt.next() {
+                        let mut next = attempt.next();
+                        while let Some((_, ' ')) | Some((_, '\t')) = next {
+                            next = attempt.next();
+                        }
+                        if let Some((_, '\r')) = next {
+                            next = attempt.next();
+                        }
+                        if let Some((_, '\n')) = next {
                             self.skip_ascii_whitespace();
                             continue;
                         }
@@ -201,6 +210,31 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn backslash_lf_span() {
+        assert_eq!(
+            unescape("a\\\n    b\\q", Mode::MultiLine).collect::<Result<Vec<_>>>(),
+            Err(Error::InvalidEscape(Span { start: 8, end: 10 })),
+        );
+    }
+
+    #[test]
+    fn backslash_cr_lf() -> Result<()> {
+        assert_eq!(
+            unescape("a\\\r\n    \t\r\n    b", Mode::MultiLine).collect::<Result<String>>()?,
+            "ab",
+        );
+        assert_eq!(
+            unescape("a\\  \t\r\n    b", Mode::MultiLine).collect::<Result<String>>()?,
+            "ab",
+        );
+        assert_eq!(
+            unescape("a\\ b", Mode::MultiLine).collect::<Result<Vec<_>>>(),
+            Err(Error::InvalidEscape(Span { start: 1, end: 3 })),
+        );
+        Ok(())
+    }
+
     #[test]
     fn backslash_n() -> Result<()> {
         assert_eq!(
@@ -0,0 +1,133 @@
+use super::{
+    options::FormatOptions,
+    report::{Event, FormatReport},
+};
+use crate::{
+    key::{Segment, Style},
+    path::{Path, Step},
+};
+use std::{cell::RefCell, rc::Rc};
+
+/// Rendering context.
+///
+/// The options of the rendering, the position and the path of the value being
+/// written and the report it adds events to, if any.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Context {
+    pub(crate) options: Rc<FormatOptions>,
+    /// Whether the comments of a table are written by the enclosing value.
+    pub(crate) enclosed: bool,
+    /// The indent of the line the value is written on.
+    pub(crate) indent: usize,
+    /// The column the value starts at.
+    pub(crate) column: usize,
+    /// The width of what follows the value on its line.
+    pub(crate) trailing: usize,
+    /// Whether the value is written only to measure its width.
+    pub(crate) measuring: bool,
+    path: Vec<Step<'static>>,
+    report: Option<Rc<RefCell<FormatReport>>>,
+}
+
+impl Context {
+    /// Returns the context with the options.
+    pub(crate) fn options(self, options: FormatOptions) -> Self {
+        Self {
+            options: Rc::new(options),
+            ..self
+        }
+    }
+
+    /// Returns the context reporting to the report.
+    pub(crate) fn report_to(self, report: &Rc<RefCell<FormatReport>>) -> Self {
+        Self {
+            report: Some(report.clone()),
+            ..self
+        }
+    }
+
+    /// Returns the context of the value starting at the column.
+    pub(crate) fn at(self, column: usize) -> Self {
+        Self { column, ..self }
+    }
+
+    /// Returns the context of the value followed by the width on its line.
+    pub(crate) fn followed_by(self, trailing: usize) -> Self {
+        Self { trailing, ..self }
+    }
+
+    /// Returns the context of the value starting a line with the indent.
+    pub(crate) fn line(self, indent: usize) -> Self {
+        Self {
+            indent,
+            column: indent,
+            ..self
+        }
+    }
+
+    /// Returns the context to measure the single-line width of the value.
+    pub(crate) fn measuring(&self) -> Self {
+        Self {
+            options: self.options.clone(),
+            enclosed: true,
+            indent: self.indent,
+            column: self.column,
+            trailing: self.trailing,
+            measuring: true,
+            path: Vec::new(),
+            report: None,
+        }
+    }
+
+    /// Returns the context of the value at the key.
+    pub(crate) fn key(&self, key: &str) -> Self {
+        self.child(|| Step::Key(Segment::new(key.to_owned())))
+    }
+
+    /// Returns the context of the array element at the index.
+    pub(crate) fn index(&self, index: usize) -> Self {
+        self.child(|| Step::Index(index))
+    }
+
+    fn child(&self, step: impl FnOnce() -> Step<'static>) -> Self {
+        // The path is only needed to report.
+        let mut path = Vec::new();
+        if self.report.is_some() {
+            path.extend(self.path.iter().cloned());
+            path.push(step());
+        }
+        Self {
+            options: self.options.clone(),
+            enclosed: true,
+            indent: self.indent,
+            column: self.column,
+            trailing: 0,
+            measuring: self.measuring,
+            path,
+            report: self.report.clone(),
+        }
+    }
+
+    /// Adds the event about the value to the report.
+    pub(crate) fn report(&self, event: impl FnOnce(Path<'static>) -> Event) {
+        if let Some(report) = &self.report {
+            let path = Path(self.path.clone());
+            report.borrow_mut().0.push(event(path));
+        }
+    }
+
+    /// Reports the key if it is written in another style than it has in the
+    /// input.
+    pub(crate) fn report_key(&self, key: &str, style: Option<Style>) {
+        let segment = Segment::new(key);
+        if style.is_some_and(|style| style != segment.style()) {
+            let written = segment.to_string();
+            self.report(|path| Event::Requoted { path, written });
+        }
+    }
+}
+
+/// Returns the width of the text.
+pub(crate) fn width(text: &str) -> usize {
+    text.chars().count()
+}
@@ -1,16 +1,22 @@
 // FIXME: [infer type for a closure argument](https://github.com/rust-lang/rust/issues/41078)
 
-use super::inline::Inline;
+use super::{
+    context::{width, Context},
+    inline::{table_comments, Inline},
+    line_ending::{LineEnding, LineEndingAdapter},
+    options::{Alignment, FormatOptions},
+    report::{Event, FormatReport},
+};
 use crate::{
     comment::Comments,
-    key::{Key, Segment},
+    key::{Key, Segment, Style},
     value::{Array, Item, Table, Value},
 };
 use derive_new::new;
 use itertools::{Either, Itertools};
 use std::{
     borrow::Borrow,
-    fmt::{self, Debug, Display, Formatter},
+    fmt::{self, Debug, Display, Formatter, Write},
     iter::FromIterator,
     vec,
 };
@@ -18,12 +24,14 @@ use std::{
 /// Independent.
 ///
 /// Only the table value can be independent.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct Independent<'a, T, F, G = F> {
     branch: Option<&'a Branch<'a, G>>,
     comments: Option<&'a Comments>,
     table: T,
     is_inline: F,
+    line_ending: LineEnding,
+    context: Context,
 }
 
 impl<T, F: Fn(&[&str]) -> bool> Independent<'_, T, F> {
@@ -33,10 +41,47 @@ impl<T, F: Fn(&[&str]) -> bool> Independent<'_, T, F> {
             comments: None,
             table,
             is_inline,
+            line_ending: LineEnding::Lf,
+            context: Context::default(),
+        }
+    }
+}
+
+impl<T, F, G> Independent<'_, T, F, G> {
+    /// Sets the line ending of the output.
+    pub fn line_ending(self, line_ending: LineEnding) -> Self {
+        Self {
+            line_ending,
+            ..self
+        }
+    }
+
+    /// Sets the format options.
+    pub fn options(self, options: FormatOptions) -> Self {
+        Self {
+            context: self.context.options(options),
+            ..self
         }
     }
 }
 
+impl<T, F, G> Independent<'_, T, F, G>
+where
+    Self: Display,
+{
+    /// Renders the table along with the report of its possibly lossy events.
+    pub fn render(self) -> (String, FormatReport) {
+        let report = Default::default();
+        let independent = Independent {
+            context: self.context.clone().report_to(&report),
+            ..self
+        };
+        let output = independent.to_string();
+        drop(independent);
+        (output, report.take())
+    }
+}
+
 impl<T, F, G> Display for Independent<'_, T, F, G>
 where
     T: Borrow<Table>,
@@ -44,13 +89,54 @@ where
     G: Fn(&[&str]) -> bool,
 {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let (leafs, branches) = self
-            .table
-            .borrow()
-            .partition(self.branch, self.is_inline.borrow());
+        let options = &self.context.options;
+        if self.branch.is_none() && !options.final_newline {
+            let options = FormatOptions {
+                final_newline: true,
+                ..FormatOptions::clone(options)
+            };
+            let independent = Independent {
+                branch: self.branch,
+                comments: self.comments,
+                table: self.table.borrow(),
+                is_inline: self.is_inline.borrow(),
+                line_ending: self.line_ending,
+                context: self.context.clone().options(options),
+            };
+            let output = if f.alternate() {
+                format!("{:#}", independent)
+            } else {
+                independent.to_string()
+            };
+            let line_ending = self.line_ending.as_str();
+            return f.write_str(output.strip_suffix(line_ending).unwrap_or(&output));
+        }
+        if self.line_ending != LineEnding::Lf {
+            let independent = Independent {
+                branch: self.branch,
+                comments: self.comments,
+                table: self.table.borrow(),
+                is_inline: self.is_inline.borrow(),
+                line_ending: LineEnding::Lf,
+                context: self.context.clone(),
+            };
+            let alternate = f.alternate();
+            let mut adapter = LineEndingAdapter::new(f, self.line_ending);
+            return if alternate {
+                write!(adapter, "{:#}", independent)
+            } else {
+                write!(adapter, "{}", independent)
+            };
+        }
+        let (mut leafs, branches) =
+            self.table
+                .borrow()
+                .partition(self.branch, self.is_inline.borrow(), &self.context);
         if let Some(branch) = self.branch {
             if !leafs.is_empty() || branches.is_empty() {
-                writeln!(f)?;
+                for _ in 0..options.blank_lines {
+                    writeln!(f)?;
+                }
                 if let Some(comments) = self.comments {
                     write!(f, "{}", comments.pre())?;
                 }
@@ -62,8 +148,16 @@ where
                     write!(f, "{}", comments.post())?;
                 }
                 writeln!(f)?;
+            } else if let Some(comments) = self.comments.filter(|comments| !comments.is_empty()) {
+                // The header is implied by the headers of the branches.
+                let comments = comments.clone();
+                self.context
+                    .report(|path| Event::OmittedComments { path, comments });
             }
         }
+        if let Alignment::Aligned { max_column } = options.alignment {
+            align(&mut leafs, max_column);
+        }
         for leaf in leafs {
             Display::fmt(&leaf, f)?;
         }
@@ -75,13 +169,20 @@ where
 }
 
 /// Independent kind.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Kind<T, U = T> {
     ArrayOfTables(T),
     Table(U),
 }
 
 impl<T> Kind<T> {
+    pub fn as_ref(&self) -> Kind<&T> {
+        match self {
+            Self::ArrayOfTables(t) => Kind::ArrayOfTables(t),
+            Self::Table(t) => Kind::Table(t),
+        }
+    }
+
     pub fn into_inner(self) -> T {
         match self {
             Self::ArrayOfTables(t) => t,
@@ -100,9 +201,9 @@ impl ArrayOfTables for Array {
     /// Extracts the array of tables if it is an array of tables.
     fn as_array_of_tables(&self) -> Option<Vec<(&Comments, &Table)>> {
         self.iter()
-            .map(|Item { comments, value }| {
-                let table = value.as_table()?;
-                Some((comments, table))
+            .map(|item| {
+                let table = item.value.as_table()?;
+                Some((&item.comments, table))
             })
             .collect()
     }
@@ -119,6 +220,7 @@ trait Partition<'a> {
         &'a self,
         branch: Option<&'a Branch<F>>,
         is_inline: &'a F,
+        context: &Context,
     ) -> (Vec<Leaf>, Vec<Branch<F>>);
 }
 
@@ -127,54 +229,103 @@ impl<'a> Partition<'a> for Table {
         &'a self,
         branch: Option<&'a Branch<F>>,
         is_inline: &'a F,
+        context: &Context,
     ) -> (Vec<Leaf>, Vec<Branch<F>>) {
-        self.iter()
-            .partition_map(move |(segment, Item { comments, value })| {
-                let key = branch
-                    .map(|branch| {
-                        let mut key = branch.key();
-                        key.push(segment);
-                        key
-                    })
-                    .unwrap_or(vec![segment]);
-                match value {
-                    Value::Array(array) if array.is_array_of_tables() && !is_inline(&key) => {
-                        let array_of_tables = array.as_array_of_tables().unwrap();
-                        Either::Right(Branch::new(
-                            branch,
-                            comments,
-                            segment,
-                            Kind::ArrayOfTables(array_of_tables),
-                            is_inline,
-                        ))
-                    }
-                    Value::Table(table) if !is_inline(&key) => Either::Right(Branch::new(
+        self.iter().partition_map(move |(segment, item)| {
+            let (comments, value) = (&item.comments, &item.value);
+            let key = branch
+                .map(|branch| {
+                    let mut key = branch.key();
+                    key.push(segment);
+                    key
+                })
+                .unwrap_or(vec![segment]);
+            let context = context.key(segment);
+            match value {
+                Value::Array(array) if array.is_array_of_tables() && !is_inline(&key) => {
+                    let array_of_tables = array.as_array_of_tables().unwrap();
+                    Either::Right(Branch::new(
+                        branch,
+                        comments,
+                        segment,
+                        item.key_style,
+                        Kind::ArrayOfTables(array_of_tables),
+                        is_inline,
+                        context,
+                    ))
+                }
+                // An inline table can't hold the comments of its items.
+                Value::Table(table) if !is_inline(&key) || !table_comments(table).is_empty() => {
+                    Either::Right(Branch::new(
                         branch,
                         comments,
                         segment,
+                        item.key_style,
                         Kind::Table(table),
                         is_inline,
-                    )),
-                    _ => Either::Left(Leaf::new(comments, segment, value)),
+                        context,
+                    ))
                 }
-            })
+                _ => Either::Left(Leaf::new(comments, segment, item.key_style, value, context)),
+            }
+        })
     }
 }
 
 /// Leaf.
-#[derive(Clone, Copy, Debug, new)]
+#[derive(Clone, Debug, new)]
 struct Leaf<'a> {
     comments: &'a Comments,
     segment: &'a str,
+    style: Option<Style>,
     value: &'a Value,
+    context: Context,
+    /// The width the key is padded to.
+    #[new(default)]
+    padding: usize,
 }
 
 impl Display for Leaf<'_> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.context.report_key(self.segment, self.style);
         write!(f, "{}", self.comments.pre())?;
-        write!(f, "{} = ", Segment::new(self.segment))?;
-        Display::fmt(&Inline::new(self.value), f)?;
-        writeln!(f, "{}", self.comments.post())
+        let segment = Segment::new(self.segment).to_string();
+        let key = format!(
+            "{}{:padding$}{}",
+            segment,
+            "",
+            self.context.options.separator,
+            padding = self.padding.saturating_sub(width(&segment)),
+        );
+        f.write_str(&key)?;
+        let post = self.comments.post().to_string();
+        let context = self.context.clone().at(width(&key));
+        let context = context.followed_by(width(&post));
+        Display::fmt(&Inline(self.value, context), f)?;
+        writeln!(f, "{}", post)
+    }
+}
+
+/// Pads the keys of each run of leaves to the width of the longest one, up to
+/// the maximum column.
+fn align(leafs: &mut [Leaf], max_column: Option<usize>) {
+    let starts_run = |leaf: &Leaf| leaf.comments.iter().any(|comment| comment.is_pre());
+    let mut start = 0;
+    while start < leafs.len() {
+        let end = leafs[start + 1..]
+            .iter()
+            .position(starts_run)
+            .map_or(leafs.len(), |index| start + 1 + index);
+        let run = &mut leafs[start..end];
+        let widths = run
+            .iter()
+            .map(|leaf| width(&Segment::new(leaf.segment).to_string()));
+        let padding = widths.max().unwrap_or_default();
+        let padding = max_column.map_or(padding, |max_column| padding.min(max_column));
+        for leaf in run {
+            leaf.padding = padding;
+        }
+        start = end;
     }
 }
 
@@ -184,8 +335,10 @@ struct Branch<'a, F> {
     parent: Option<&'a Branch<'a, F>>,
     comments: &'a Comments,
     segment: &'a str,
+    style: Option<Style>,
     value: Kind<Vec<(&'a Comments, &'a Table)>, &'a Table>,
     is_inline: &'a F,
+    context: Context,
 }
 
 impl<F> Branch<'_, F> {
@@ -210,14 +363,17 @@ impl<F> Branch<'_, F> {
 
 impl<F: Fn(&[&str]) -> bool> Display for Branch<'_, F> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.context.report_key(self.segment, self.style);
         match &self.value {
             Kind::ArrayOfTables(array_of_tables) => {
-                for (comments, table) in array_of_tables.iter() {
+                for (index, (comments, table)) in array_of_tables.iter().enumerate() {
                     let independent = Independent {
                         branch: Some(self),
                         comments: Some(comments),
                         table: *table,
                         is_inline: self.is_inline,
+                        line_ending: LineEnding::Lf,
+                        context: self.context.index(index),
                     };
                     Display::fmt(&independent, f)?;
                 }
@@ -228,6 +384,8 @@ impl<F: Fn(&[&str]) -> bool> Display for Branch<'_, F> {
                     comments: Some(self.comments),
                     table: *table,
                     is_inline: self.is_inline,
+                    line_ending: LineEnding::Lf,
+                    context: self.context.clone(),
                 };
                 Display::fmt(&independent, f)?;
             }
@@ -313,6 +471,115 @@ mod test {
         // println!("independent:\n{:#}", independent);
     }
 
+    #[test]
+    fn line_ending() {
+        let table = Table::from_iter(indexmap! {
+            "a" => Value::from(true),
+            "b" => Value::from_iter(indexmap! {
+                "ba" => Value::from(true),
+            }),
+        });
+        let independent = Independent::new(&table, |_key| false).line_ending(LineEnding::CrLf);
+        assert_eq!(
+            independent.to_string(),
+            "a = true\r\n\r\n[b]\r\nba = true\r\n",
+        );
+        let independent = Independent::new(&table, |_key| false);
+        assert_eq!(independent.to_string(), "a = true\n\n[b]\nba = true\n");
+    }
+
+    #[test]
+    fn comments() {
+        let table = crate::parse("[a]\n# b\nc = 1 # d\n[a.e]\nf = 2\n").unwrap();
+        let independent = Independent::new(&table, |_key| true);
+        assert_eq!(
+            independent.to_string(),
+            "\n[a]\n# b\nc = 1 # d\ne = { f = 2 }\n",
+        );
+    }
+
+    #[test]
+    fn render() {
+        let mut table = crate::parse("[a.b]\n'c d' = 1\n[e]\nf = [{ g = 1 }]").unwrap();
+        table["a"].comments = crate::parse("# a\nh = 1").unwrap()["h"].comments.clone();
+        let (output, report) = Independent::new(&table, |_key| false).render();
+        assert_eq!(output, Independent::new(&table, |_key| false).to_string());
+        // The key is written in the literal string it has in the input.
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].path().to_string(), "a");
+        assert!(matches!(report[0], Event::OmittedComments { .. }));
+        let table = crate::parse("[\"a\".'b c']\n\"d e\" = 1\n").unwrap();
+        let (output, report) = Independent::new(&table, |_key| false).render();
+        assert_eq!(output, "\n[a.'b c']\n'd e' = 1\n");
+        let paths = report.iter().map(|event| event.path().to_string());
+        assert_eq!(paths.collect::<Vec<_>>(), ["a", "a.'b c'.'d e'"]);
+        assert!(report
+            .iter()
+            .all(|event| matches!(event, Event::Requoted { .. })));
+    }
+
+    #[test]
+    fn options() {
+        let table = crate::parse("a = [1, 2]\nb = { c = 1 }\n[d]\ne = 1\n[f]\n").unwrap();
+        let options = FormatOptions::new()
+            .indent("\t")
+            .trailing_comma(false)
+            .blank_lines(2)
+            .inline_table_padding(false)
+            .separator("=")
+            .final_newline(false);
+        let independent = Independent::new(&table, |key| key == ["b"]).options(options);
+        assert_eq!(
+            format!("{:#}", independent),
+            "a=[\n\t1,\n\t2\n]\nb={c=1}\n\n\n[d]\ne=1\n\n\n[f]",
+        );
+        let independent = independent.line_ending(LineEnding::CrLf);
+        assert!(independent.to_string().ends_with("e=1\r\n\r\n\r\n[f]"));
+    }
+
+    #[test]
+    fn max_width() {
+        let input =
+            "a = [1, 2, 3]\nb = [[1, 2], [3, 4, 5, 6, 7, 8]]\nc = { d = [1, 2, 3, 4], e = 1 }";
+        let table = crate::parse(input).unwrap();
+        let options = FormatOptions::new().max_width(20);
+        let independent = Independent::new(&table, |_key| true).options(options);
+        let expected = "a = [1, 2, 3]\n\
+                        b = [\n    [1, 2],\n    [\n        3,\n        4,\n        5,\n        6,\n        7,\n        8,\n    ],\n]\n\
+                        c = { d = [\n    1,\n    2,\n    3,\n    4,\n], e = 1 }\n";
+        assert_eq!(independent.to_string(), expected);
+        assert_eq!(format!("{:#}", independent), expected);
+        assert_eq!(crate::parse(expected).unwrap(), table);
+        // The inner array and its comma fit exactly.
+        let table = crate::parse("a = [[1, 2, 3, 4, 5, 6]] # b").unwrap();
+        let independent = |max_width| {
+            let options = FormatOptions::new().max_width(max_width);
+            Independent::new(&table, |_key| true)
+                .options(options)
+                .to_string()
+        };
+        assert_eq!(independent(23), "a = [\n    [1, 2, 3, 4, 5, 6],\n] # b\n");
+        assert_eq!(
+            independent(22),
+            "a = [\n    [\n        1,\n        2,\n        3,\n        4,\n        5,\n        6,\n    ],\n] # b\n",
+        );
+    }
+
+    #[test]
+    fn alignment() {
+        let input =
+            "a = 1\nbcd = 2\nlong_key = 3\n# e\nf = 4\ngh = { i = 5 }\n\n[j]\nk = 6\nlm = 7\n";
+        let table = crate::parse(input).unwrap();
+        let options = FormatOptions::new().alignment(Alignment::Aligned {
+            max_column: Some(3),
+        });
+        let independent = Independent::new(&table, |key| key == ["gh"]).options(options);
+        assert_eq!(
+            independent.to_string(),
+            "a   = 1\nbcd = 2\nlong_key = 3\n# e\nf  = 4\ngh = { i = 5 }\n\n[j]\nk  = 6\nlm = 7\n",
+        );
+    }
+
     #[test]
     fn test() {
         let is_inline = |_key: &[&str]| true;
@@ -1,41 +1,89 @@
+use super::{
+    context::{width, Context},
+    options::FormatOptions,
+    report::{Event, FormatReport},
+};
 use crate::{
+    comment::Comments,
     key::Segment,
-    value::{Array, Item, Table, Value},
+    quotes::{Quoted, Quotes},
+    value::{Array, Item, Primitive, Table, Value},
 };
 use derive_more::Deref;
 use derive_new::new;
-use itertools::Itertools;
-use log::warn;
 use pad_adapter::PadAdapter;
 use std::fmt::{self, Debug, Display, Formatter, Write};
 
 /// Inline.
+///
+/// Arrays with comments are written one element per line. The comments of
+/// the items of inline tables are written on the line enclosing the table.
 #[derive(Clone, Debug, Deref, new)]
-pub struct Inline<T>(T);
+pub struct Inline<T>(#[deref] pub(crate) T, #[new(default)] pub(crate) Context);
+
+impl<T> Inline<T> {
+    /// Sets the format options.
+    pub fn options(self, options: FormatOptions) -> Self {
+        Inline(self.0, self.1.options(options))
+    }
+}
+
+impl<T> Inline<T>
+where
+    Self: Display,
+{
+    /// Renders the value along with the report of its possibly lossy events.
+    pub fn render(self) -> (String, FormatReport) {
+        let report = Default::default();
+        let inline = Inline(self.0, self.1.report_to(&report));
+        let output = inline.to_string();
+        drop(inline);
+        (output, report.take())
+    }
+}
 
 impl Display for Inline<Item> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        Display::fmt(&Inline::new(&self.0), f)
+        Display::fmt(&Inline(&self.0, self.1.clone()), f)
     }
 }
 
 impl Display for Inline<&Item> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        Display::fmt(&Inline::new(&self.value), f)
+        Display::fmt(&Inline(&self.value, self.1.clone()), f)
     }
 }
 
 impl Display for Inline<Value> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        Display::fmt(&Inline::new(&self.0), f)
+        Display::fmt(&Inline(&self.0, self.1.clone()), f)
     }
 }
 
 impl Display for Inline<&Value> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self.0 {
-            Value::Array(array) => Display::fmt(&Inline::new(array), f),
-            Value::Table(table) => Display::fmt(&Inline::new(table), f),
+            Value::Array(array) => Display::fmt(&Inline(array, self.1.clone()), f),
+            Value::Table(table) => Display::fmt(&Inline(table, self.1.clone()), f),
+            Value::Primitive(Primitive::String(quoted)) if !quoted.can_hold() => {
+                // Double quotes can hold any string.
+                let mut requoted = Quoted::new(quoted.as_str());
+                if !requoted.can_hold() {
+                    requoted = Quoted::SingleLine(Quotes::Double(quoted.as_str()));
+                }
+                let written = requoted.to_string();
+                f.write_str(&written)?;
+                self.1
+                    .report(|path| Event::Representation { path, written });
+                Ok(())
+            }
+            Value::Primitive(primitive) if primitive.is_respelled() => {
+                let written = primitive.to_string();
+                f.write_str(&written)?;
+                self.1
+                    .report(|path| Event::Representation { path, written });
+                Ok(())
+            }
             Value::Primitive(primitive) => Display::fmt(primitive, f),
         }
     }
@@ -43,7 +91,7 @@ impl Display for Inline<&Value> {
 
 impl Display for Inline<Array> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        Display::fmt(&Inline::new(&self.0), f)
+        Display::fmt(&Inline(&self.0, self.1.clone()), f)
     }
 }
 
@@ -51,28 +99,52 @@ impl Display for Inline<&Array> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.write_char('[')?;
         if !self.is_empty() {
-            if f.alternate() {
+            let comments = self
+                .iter()
+                .map(|item| {
+                    let mut comments = item.comments.clone();
+                    comments.append(&mut value_comments(&item.value));
+                    comments
+                })
+                .collect::<Vec<_>>();
+            let options = &self.1.options;
+            let alternate = options.max_width.is_none() && f.alternate();
+            let wrap = match options.max_width {
+                Some(max_width) if !self.1.measuring => {
+                    let single_line = Inline(self.0, self.1.measuring()).to_string();
+                    self.1.column + width(&single_line) + self.1.trailing > max_width
+                }
+                _ => alternate,
+            };
+            if wrap || comments.iter().any(|comments| !comments.is_empty()) {
                 // Alternate.
+                let indent = self.1.indent + width(&options.indent);
                 f.write_char('\n')?;
-                let mut pad_adapter = PadAdapter::new(f);
-                for Item { comments, value } in self.iter() {
+                let mut pad_adapter = PadAdapter::with_padding(f, &options.indent);
+                for (index, (Item { value, .. }, comments)) in self.iter().zip(comments).enumerate()
+                {
+                    let comma = index + 1 != self.len() || options.trailing_comma;
+                    let post = comments.post().to_string();
+                    let context = self.1.index(index).line(indent);
+                    let inline = Inline(value, context.followed_by(comma as usize + width(&post)));
                     write!(pad_adapter, "{}", comments.pre())?;
-                    write!(pad_adapter, "{:#},", Inline::new(value))?;
-                    writeln!(pad_adapter, "{}", comments.post())?;
+                    if alternate {
+                        write!(pad_adapter, "{:#}", inline)?;
+                    } else {
+                        write!(pad_adapter, "{}", inline)?;
+                    }
+                    if comma {
+                        pad_adapter.write_char(',')?;
+                    }
+                    writeln!(pad_adapter, "{}", post)?;
                 }
             } else {
                 // Non-alternate.
-                for (index, Item { comments, value }) in self.iter().enumerate() {
+                for (index, Item { value, .. }) in self.iter().enumerate() {
                     if index != 0 {
                         write!(f, ", ")?;
                     }
-                    write!(f, "{}", Inline::new(value))?;
-                    if !comments.is_empty() {
-                        warn!(
-                            r#"comments were omitted: "{}""#,
-                            comments.iter().format(r#"", ""#)
-                        );
-                    }
+                    write!(f, "{}", Inline(value, self.1.index(index)))?;
                 }
             }
         }
@@ -82,37 +154,101 @@ impl Display for Inline<&Array> {
 
 impl Display for Inline<Table> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        Display::fmt(&Inline::new(&self.0), f)
+        Display::fmt(&Inline(&self.0, self.1.clone()), f)
     }
 }
 
 impl Display for Inline<&Table> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if !self.1.enclosed {
+            omit_comments(&self.1, self.0);
+        }
+        let options = &self.1.options;
         f.write_char('{')?;
         if !self.is_empty() {
-            f.write_char(' ')?;
-            for (index, (segment, Item { comments, value })) in self.iter().enumerate() {
+            if options.inline_table_padding {
+                f.write_char(' ')?;
+            }
+            // The columns are only needed to wrap arrays.
+            let columns = options.max_width.is_some() && !self.1.measuring;
+            let mut column = self.1.column + 1 + options.inline_table_padding as usize;
+            for (index, (segment, item)) in self.iter().enumerate() {
                 if index != 0 {
                     write!(f, ", ")?;
+                    column += 2;
+                }
+                let key = format!("{}{}", Segment::new(segment), options.separator);
+                column += width(&key);
+                let mut context = self.1.key(segment).at(column);
+                // The last value is followed by the closing brace.
+                if index + 1 == self.len() {
+                    let trailing = self.1.trailing + 1 + options.inline_table_padding as usize;
+                    context = context.followed_by(trailing);
                 }
-                write!(f, "{} = ", Segment::new(segment))?;
-                Display::fmt(&Inline::new(value), f)?;
-                if !comments.is_empty() {
-                    warn!(
-                        r#"comments were omitted: "{}""#,
-                        comments.iter().format(r#"", ""#)
-                    );
+                context.report_key(segment, item.key_style);
+                f.write_str(&key)?;
+                if columns {
+                    let value = Inline(&item.value, context).to_string();
+                    column = match value.rfind('\n') {
+                        Some(index) => self.1.indent + width(&value[index + 1..]),
+                        None => column + width(&value),
+                    };
+                    f.write_str(&value)?;
+                } else {
+                    Display::fmt(&Inline(&item.value, context), f)?;
                 }
             }
-            f.write_char(' ')?;
+            if options.inline_table_padding {
+                f.write_char(' ')?;
+            }
         }
         f.write_char('}')
     }
 }
 
+/// Reports the comments of the items of the inline table, nested tables
+/// included.
+fn omit_comments(context: &Context, table: &Table) {
+    for (segment, item) in table.iter() {
+        let context = context.key(segment);
+        if !item.comments.is_empty() {
+            let comments = item.comments.clone();
+            context.report(|path| Event::OmittedComments { path, comments });
+        }
+        if let Value::Table(table) = &item.value {
+            omit_comments(&context, table);
+        }
+    }
+}
+
+/// Returns the comments of the items of the inline table, nested tables
+/// included, which can only be written on the line enclosing it.
+pub(crate) fn table_comments(table: &Table) -> Comments {
+    let mut comments = Comments::new();
+    for Item {
+        comments: item_comments,
+        value,
+        ..
+    } in table.values()
+    {
+        comments.extend(item_comments.iter().cloned());
+        comments.append(&mut value_comments(value));
+    }
+    comments
+}
+
+/// Returns the comments of the value, if it is an inline table.
+pub(crate) fn value_comments(value: &Value) -> Comments {
+    match value {
+        Value::Table(table) => table_comments(table),
+        _ => Comments::new(),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::value::{Float, Integer};
     use indexmap::indexmap;
     use std::iter::FromIterator;
 
@@ -168,4 +304,105 @@ mod test {
         println!("inline:\n{}", inline);
         // println!("inline:\n{:#}", inline);
     }
+
+    #[test]
+    fn comments() {
+        let table = crate::parse("a = [\n  # b\n  1, # c\n  2,\n]").unwrap();
+        assert_eq!(
+            Inline::new(&table["a"]).to_string(),
+            "[\n    # b\n    1, # c\n    2,\n]",
+        );
+        let table = crate::parse("a = [1, { b = 2 }]\n[c]\n# d\ne = 3 # f").unwrap();
+        let mut array = table["a"].as_array().unwrap().clone();
+        array[1] = Item::from(table["c"].value.clone());
+        assert_eq!(
+            Inline::new(&array).to_string(),
+            "[\n    1,\n    # d\n    { e = 3 }, # f\n]",
+        );
+        assert_eq!(Inline::new(&table["a"]).to_string(), "[1, { b = 2 }]");
+    }
+
+    #[test]
+    fn options() {
+        let table = crate::parse("a = [1, 2]\nb = { c = [3] }").unwrap();
+        let options = FormatOptions::new()
+            .indent("  ")
+            .inline_table_padding(false)
+            .separator(": ");
+        assert_eq!(
+            format!("{:#}", Inline::new(&table).options(options.clone())),
+            "{a: [\n  1,\n  2,\n], b: {c: [\n  3,\n]}}",
+        );
+        assert_eq!(
+            Inline::new(&table).options(options).to_string(),
+            "{a: [1, 2], b: {c: [3]}}",
+        );
+    }
+
+    #[test]
+    fn render() {
+        let mut table = crate::parse("a = { 'b c' = 1 }\n[d]\n# e\nf = 'g'").unwrap();
+        let d = table.remove("d").unwrap();
+        table["a"]
+            .as_table_mut()
+            .unwrap()
+            .insert("d".to_owned(), d.clone());
+        table["a"]["d"]["f"].value =
+            Value::from(Quoted::SingleLine(Quotes::Single("it's".to_owned())));
+        let (output, report) = Inline::new(&table).render();
+        assert_eq!(output, r#"{ a = { 'b c' = 1, d = { f = "it's" } } }"#);
+        let events = report.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                "comments of `a.d.f` were omitted: `# e`",
+                r#"`a.d.f` is written as `"it's"`"#,
+            ],
+        );
+        let (_, report) = Inline::new(&d).render();
+        assert_eq!(report[0].path().to_string(), "f");
+        let table = crate::parse(r#"a = { "b" = 1, "c d" = 2, 'e f' = 3, g = 4 }"#).unwrap();
+        let (output, report) = Inline::new(&table).render();
+        assert_eq!(output, "{ a = { b = 1, 'c d' = 2, 'e f' = 3, g = 4 } }");
+        let events = report.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                "key of `a.b` is written as `b`",
+                "key of `a.'c d'` is written as `'c d'`",
+            ],
+        );
+    }
+
+    #[test]
+    fn render_primitives() {
+        let mut table = crate::parse("a = 0xff\nb = 1.50\nc = 0o17\nd = 2.50").unwrap();
+        if let Value::Primitive(Primitive::Integer(Integer::Hex(value, _))) = &mut table["a"].value
+        {
+            *value = -255;
+        }
+        if let Value::Primitive(Primitive::Float(Float::Decimal(value), _)) = &mut table["b"].value
+        {
+            *value = 2.5;
+        }
+        let (output, report) = Inline::new(&table).render();
+        assert_eq!(output, "{ a = -255, b = 2.5, c = 0o17, d = 2.50 }");
+        let events = report.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            ["`a` is written as `-255`", "`b` is written as `2.5`"]
+        );
+    }
+
+    #[test]
+    fn max_width() {
+        let table = crate::parse("a = { b = [1, 2] }").unwrap();
+        let inline = |max_width| {
+            let options = FormatOptions::new().max_width(max_width);
+            Inline::new(&table["a"]).options(options).to_string()
+        };
+        // The array and the closing brace fit exactly.
+        assert_eq!(inline(14), "{ b = [1, 2] }");
+        assert_eq!(inline(13), "{ b = [\n    1,\n    2,\n] }");
+    }
 }
@@ -0,0 +1,77 @@
+use std::fmt::{self, Display, Formatter, Write};
+
+/// Line ending.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// Detects the line ending of the input by its first line.
+    ///
+    /// Defaults to `Lf` if there is no line ending.
+    pub fn detect(input: &str) -> Self {
+        match input.find('\n') {
+            Some(index) if input[..index].ends_with('\r') => Self::CrLf,
+            _ => Self::Lf,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+}
+
+impl Display for LineEnding {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Line ending adapter.
+///
+/// Replaces line feeds, which are not already preceded by a carriage return,
+/// with the line ending.
+pub(crate) struct LineEndingAdapter<'a, 'b> {
+    f: &'a mut Formatter<'b>,
+    line_ending: LineEnding,
+    is_cr: bool,
+}
+
+impl<'a, 'b> LineEndingAdapter<'a, 'b> {
+    pub(crate) fn new(f: &'a mut Formatter<'b>, line_ending: LineEnding) -> Self {
+        Self {
+            f,
+            line_ending,
+            is_cr: false,
+        }
+    }
+}
+
+impl Write for LineEndingAdapter<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.line_ending == LineEnding::Lf {
+            return self.f.write_str(s);
+        }
+        for (index, line) in s.split('\n').enumerate() {
+            if index != 0 {
+                if self.is_cr {
+                    self.f.write_char('\n')?;
+                } else {
+                    self.f.write_str(self.line_ending.as_str())?;
+                }
+                self.is_cr = false;
+            }
+            if !line.is_empty() {
+                self.f.write_str(line)?;
+                self.is_cr = line.ends_with('\r');
+            }
+        }
+        Ok(())
+    }
+}
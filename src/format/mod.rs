@@ -1,4 +1,14 @@
-pub use self::{independent::Independent, inline::Inline};
+pub use self::{
+    independent::Independent,
+    inline::Inline,
+    line_ending::LineEnding,
+    options::{Alignment, FormatOptions},
+    report::{Event, FormatReport},
+};
 
+mod context;
 pub mod independent;
 pub mod inline;
+mod line_ending;
+mod options;
+mod report;
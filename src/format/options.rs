@@ -0,0 +1,116 @@
+/// Format options.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormatOptions {
+    /// The indent of the elements of multi-line arrays.
+    pub indent: String,
+    /// Whether the last element of a multi-line array is followed by a comma.
+    pub trailing_comma: bool,
+    /// The number of blank lines before a table header.
+    pub blank_lines: usize,
+    /// Whether the braces of a non-empty inline table are padded with spaces.
+    pub inline_table_padding: bool,
+    /// The separator between a key and its value.
+    pub separator: String,
+    /// Whether the output ends with a line ending.
+    pub final_newline: bool,
+    /// The maximum width of a line, if any.
+    ///
+    /// Only the arrays which don't fit on their line are written one element
+    /// per line, regardless of the alternate flag.
+    pub max_width: Option<usize>,
+    pub alignment: Alignment,
+}
+
+impl FormatOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the indent.
+    pub fn indent(self, indent: impl Into<String>) -> Self {
+        Self {
+            indent: indent.into(),
+            ..self
+        }
+    }
+
+    /// Sets whether multi-line arrays have a trailing comma.
+    pub fn trailing_comma(self, trailing_comma: bool) -> Self {
+        Self {
+            trailing_comma,
+            ..self
+        }
+    }
+
+    /// Sets the number of blank lines before table headers.
+    pub fn blank_lines(self, blank_lines: usize) -> Self {
+        Self {
+            blank_lines,
+            ..self
+        }
+    }
+
+    /// Sets whether inline tables are padded.
+    pub fn inline_table_padding(self, inline_table_padding: bool) -> Self {
+        Self {
+            inline_table_padding,
+            ..self
+        }
+    }
+
+    /// Sets the key/value separator.
+    pub fn separator(self, separator: impl Into<String>) -> Self {
+        Self {
+            separator: separator.into(),
+            ..self
+        }
+    }
+
+    /// Sets whether the output ends with a line ending.
+    pub fn final_newline(self, final_newline: bool) -> Self {
+        Self {
+            final_newline,
+            ..self
+        }
+    }
+
+    /// Sets the maximum line width.
+    pub fn max_width(self, max_width: usize) -> Self {
+        Self {
+            max_width: Some(max_width),
+            ..self
+        }
+    }
+
+    /// Sets the alignment of the separators.
+    pub fn alignment(self, alignment: Alignment) -> Self {
+        Self { alignment, ..self }
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: "    ".to_owned(),
+            trailing_comma: true,
+            blank_lines: 1,
+            inline_table_padding: true,
+            separator: " = ".to_owned(),
+            final_newline: true,
+            max_width: None,
+            alignment: Alignment::None,
+        }
+    }
+}
+
+/// Alignment of the separators of key/value pairs.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Alignment {
+    /// The separators follow the keys.
+    #[default]
+    None,
+    /// The separators are aligned within each run of key/value pairs, which a
+    /// comment line or a table header breaks. The keys are padded up to the
+    /// maximum column, if any, longer keys are not.
+    Aligned { max_column: Option<usize> },
+}
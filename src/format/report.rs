@@ -0,0 +1,66 @@
+use crate::{comment::Comments, path::Path};
+use derive_more::{Deref, IntoIterator};
+use std::fmt::{self, Display, Formatter};
+
+/// Format report.
+///
+/// The events of a rendering which may lose some of the input, in the order
+/// of the output.
+#[derive(Clone, Debug, Default, Deref, IntoIterator, PartialEq)]
+pub struct FormatReport(pub(crate) Vec<Event>);
+
+/// Possibly lossy formatting event.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// The comments can't be written.
+    OmittedComments {
+        path: Path<'static>,
+        comments: Comments,
+    },
+    /// The value can't be written as it is represented and is written
+    /// otherwise: a string with other quotes, a negative non-decimal integer
+    /// as a decimal one, or a float without its literal once the literal no
+    /// longer spells it.
+    Representation {
+        path: Path<'static>,
+        written: String,
+    },
+    /// The key is written in another style than it has in the input, as keys
+    /// are written bare if they can be, in a literal string if they need no
+    /// escapes, and in a basic string otherwise.
+    Requoted {
+        path: Path<'static>,
+        written: String,
+    },
+}
+
+impl Event {
+    /// Returns the path of the value the event is about.
+    pub fn path(&self) -> &Path<'static> {
+        match self {
+            Self::OmittedComments { path, .. }
+            | Self::Representation { path, .. }
+            | Self::Requoted { path, .. } => path,
+        }
+    }
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::OmittedComments { path, comments } => {
+                write!(f, "comments of `{}` were omitted:", path)?;
+                for comment in comments.iter() {
+                    write!(f, " `{}`", comment)?;
+                }
+                Ok(())
+            }
+            Self::Representation { path, written } => {
+                write!(f, "`{}` is written as `{}`", path, written)
+            }
+            Self::Requoted { path, written } => {
+                write!(f, "key of `{}` is written as `{}`", path, written)
+            }
+        }
+    }
+}
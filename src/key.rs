@@ -16,6 +16,13 @@ use std::{
 #[derive(Clone, Debug, Default, Deref, DerefMut, Eq, Hash, IntoIterator, PartialEq, new)]
 pub struct Key<'a>(#[new(default)] Vec<Segment<'a>>);
 
+impl Key<'_> {
+    /// Converts the key into an owned one.
+    pub fn into_owned(self) -> Key<'static> {
+        self.into_iter().map(Segment::into_owned).collect()
+    }
+}
+
 impl Display for Key<'_> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "{}", self.iter().format("."))
@@ -72,6 +79,29 @@ impl<'a> Segment<'a> {
         }
     }
 
+    /// Returns the style of the segment.
+    pub fn style(&self) -> Style {
+        match self {
+            Self::Unquoted(_) => Style::Bare,
+            Self::Quoted(Quoted::SingleLine(quotes)) | Self::Quoted(Quoted::MultiLine(quotes)) => {
+                match quotes {
+                    Quotes::Single(_) => Style::Literal,
+                    Quotes::Double(_) => Style::Basic,
+                }
+            }
+        }
+    }
+
+    /// Converts the segment into an owned one.
+    pub fn into_owned(self) -> Segment<'static> {
+        match self {
+            Segment::Unquoted(cow) => Segment::Unquoted(Cow::Owned(cow.into_owned())),
+            Segment::Quoted(quoted) => {
+                Segment::Quoted(quoted.map(|cow| Cow::Owned(cow.into_owned())))
+            }
+        }
+    }
+
     pub fn into_inner(self) -> Cow<'a, str> {
         match self {
             Segment::Unquoted(cow) => cow,
@@ -83,6 +113,16 @@ impl<'a> Segment<'a> {
     }
 }
 
+/// Style.
+///
+/// How a key segment is written: bare, or in a literal or a basic string.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Style {
+    Bare,
+    Literal,
+    Basic,
+}
+
 impl Deref for Segment<'_> {
     type Target = str;
 
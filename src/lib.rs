@@ -1,24 +1,56 @@
 #![cfg_attr(feature = "drain-filter", feature(drain_filter))]
 
-pub use self::parser::TomlParser;
+pub use self::{
+    de::from_str,
+    diff::{diff, Diff},
+    document::Document,
+    error::{Diagnostic, Error, ErrorKind, Location, Result},
+    escape::{Error as EscapeError, Span},
+    merge::{CommentPolicy, Merge, MergeError, MergeOptions, MergeReport, Origin, Strategy},
+    merge3::merge3,
+    parser::TomlParser,
+    patch::{Patch, PatchError},
+    path::{Path, PathError},
+    query::{Match, Selector},
+    ser::{to_string, to_table, to_value},
+};
 
+use self::value::Table;
 use lalrpop_util::lalrpop_mod;
 
 pub mod comment;
+pub mod datetime;
+pub mod de;
+pub mod diff;
+pub mod document;
 pub mod format;
 pub mod key;
+pub mod merge;
+pub mod merge3;
+pub mod patch;
+pub mod path;
+pub mod query;
+pub mod ser;
 pub mod value;
 
 mod ast;
+mod error;
 mod escape;
-mod merge;
 lalrpop_mod!(parser, "/parser.rs");
 mod quotes;
+mod validate;
+
+/// Parses the input into a table.
+pub fn parse(input: &str) -> Result<Table> {
+    TomlParser::new()
+        .parse(input)
+        .map_err(|error| Error::from_parse_error(input, error))
+}
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::format::Independent;
+    use crate::format::{Independent, LineEnding};
     use anyhow::Result;
 
     fn parse<'a>(input: &'a str) -> Result<String> {
@@ -30,6 +62,21 @@ mod test {
         // Ok(Independent::new(parser.parse(input).unwrap(), is_inline).to_string())
     }
 
+    #[test]
+    fn array_order() -> Result<()> {
+        let table = crate::parse("a = [\n    # b\n    1, # c\n    2,\n    3, # d\n]")?;
+        let array = table["a"].as_array().unwrap();
+        let values: Vec<_> = array
+            .iter()
+            .map(|item| item.as_integer().unwrap().to_string())
+            .collect();
+        assert_eq!(values, ["1", "2", "3"]);
+        assert_eq!(array[0].comments.pre().to_string(), "# b\n");
+        assert_eq!(array[0].comments.post().to_string(), " # c");
+        assert_eq!(array[2].comments.post().to_string(), " # d");
+        Ok(())
+    }
+
     #[test]
     fn temp() -> Result<()> {
         assert_eq!(
@@ -70,4 +117,60 @@ mod test {
         // assert_eq!(parse(r#""'a' = \"b\"" = false"#)?.trim(), r#""'a' = \"b\"" = false"#);
         Ok(())
     }
+
+    #[test]
+    fn date_time() -> Result<()> {
+        let input = r#"odt1 = 1979-05-27T07:32:00Z
+odt2 = 1979-05-27T00:32:00-07:00
+odt3 = 1979-05-27 00:32:00.999999+05:30
+ldt = 1979-05-27t07:32:00
+ld = 1979-05-27
+lt = 00:32:00.999999
+array = [1979-05-27, 07:32:00]
+
+[b]
+c = 1979-05-27 07:32:00
+"#;
+        assert_eq!(parse(input)?, input);
+        let table = crate::parse(input)?;
+        assert!(table["odt1"].is_date_time());
+        assert!(table["array"][0].is_date_time());
+        let table = crate::parse("table = { a = 1979-05-27T07:32:00z }")?;
+        assert!(table["table"]["a"].is_date_time());
+        Ok(())
+    }
+
+    #[test]
+    fn float() -> Result<()> {
+        let input = r#"a = +1.0
+b = 5e+22
+c = 224_617.445_991
+d = [-inf, nan, 6.626E-34]
+inf = nan
+-nan = 1.50
+"#;
+        assert_eq!(parse(input)?, input);
+        let table = crate::parse(input)?;
+        assert!(table["inf"].is_float());
+        assert!(crate::parse("+inf = 1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn crlf() -> Result<()> {
+        let input =
+            "a = 1 # Comment.\r\n\r\n[b]\r\nc = '''d\r\ne'''\r\nf = \"\"\"\\\r\n  g\"\"\"\r\n";
+        let table = crate::parse(input)?;
+        assert_eq!(
+            table["b"]["c"].as_string().map(|s| s.as_str()),
+            Some("d\r\ne")
+        );
+        assert_eq!(table["b"]["f"].as_string().map(|s| s.as_str()), Some("g"));
+        let independent = Independent::new(&table, |_: &[&str]| false);
+        let output = independent
+            .line_ending(LineEnding::detect(input))
+            .to_string();
+        assert_eq!(output, input.replace("\"\"\"\\\r\n  g", "\"\"\"g"));
+        Ok(())
+    }
 }
@@ -1,60 +1,464 @@
-use crate::value::{Array, Table, Value};
+use crate::{
+    comment::Comments,
+    key::{Key, Segment},
+    path::{Path, Step},
+    value::{Array, Item, Table, Value},
+};
+use indexmap::IndexMap;
+use std::{iter::FromIterator, mem::take};
+use thiserror::Error;
 
 /// Merge values.
-pub(crate) trait Merge {
-    fn merge(&mut self, value: Value);
+pub trait Merge {
+    /// Merges the value into this one.
+    ///
+    /// Tables are merged key by key, arrays are appended, and a table is merged
+    /// into the last table of an array of tables. Anything else is a conflict,
+    /// which leaves this value unchanged.
+    fn merge(&mut self, value: Value) -> Result<(), MergeError>;
 }
 
 impl Merge for Value {
-    fn merge(&mut self, value: Value) {
-        match self {
-            Self::Table(table) => table.merge(value),
-            Self::Array(array) => array.merge(value),
-            _ => panic!("Can't merge a primitive value."),
-        }
+    fn merge(&mut self, value: Value) -> Result<(), MergeError> {
+        check_value(self, &value)?;
+        merge_value(self, value);
+        Ok(())
     }
 }
 
 impl Merge for Table {
-    fn merge(&mut self, value: Value) {
-        match value {
-            Value::Table(other) => {
-                for (segment, mut source) in other.into_iter() {
-                    if let Some(target) = self.get_mut(&segment) {
-                        target.value.merge(source.value);
-                        target.comments.append(&mut source.comments);
-                    } else {
-                        self.insert(segment, source);
+    fn merge(&mut self, value: Value) -> Result<(), MergeError> {
+        check_table(self, &value)?;
+        merge_table(self, value);
+        Ok(())
+    }
+}
+
+impl Merge for Array {
+    fn merge(&mut self, value: Value) -> Result<(), MergeError> {
+        check_array(self, &value)?;
+        merge_array(self, value);
+        Ok(())
+    }
+}
+
+// The conflicts are checked before anything is merged, so that a failed merge
+// changes nothing.
+
+fn check_value(target: &Value, value: &Value) -> Result<(), MergeError> {
+    match target {
+        Value::Table(table) => check_table(table, value),
+        Value::Array(array) => check_array(array, value),
+        _ => Err(MergeError::new(target, value)),
+    }
+}
+
+pub(crate) fn check_table(target: &Table, value: &Value) -> Result<(), MergeError> {
+    match value {
+        Value::Table(other) => {
+            for (segment, source) in other.iter() {
+                if let Some(target) = target.get(segment) {
+                    if let Err(mut error) = check_value(&target.value, &source.value) {
+                        error.key.insert(0, segment.clone());
+                        return Err(error);
                     }
                 }
             }
-            _ => panic!("Can't merge a table value with a not-table value."),
+            Ok(())
         }
+        _ => Err(MergeError {
+            key: Vec::new(),
+            target: "table",
+            other: value.type_str(),
+        }),
     }
 }
 
-impl Merge for Array {
-    fn merge(&mut self, value: Value) {
+fn check_array(target: &Array, value: &Value) -> Result<(), MergeError> {
+    match (value, target.last()) {
+        (Value::Array(_), _) => Ok(()),
+        // A sub-table of the last table of an array of tables.
+        (Value::Table(_), Some(last)) if last.value.is_table() => check_value(&last.value, value),
+        _ => Err(MergeError {
+            key: Vec::new(),
+            target: "array",
+            other: value.type_str(),
+        }),
+    }
+}
+
+fn merge_value(target: &mut Value, value: Value) {
+    match target {
+        Value::Table(table) => merge_table(table, value),
+        Value::Array(array) => merge_array(array, value),
+        _ => unreachable!("checked merge"),
+    }
+}
+
+pub(crate) fn merge_table(target: &mut Table, value: Value) {
+    if let Value::Table(other) = value {
+        for (segment, mut source) in other.into_iter() {
+            if let Some(target) = target.get_mut(&segment) {
+                merge_value(&mut target.value, source.value);
+                target.comments.append(&mut source.comments);
+            } else {
+                target.insert(segment, source);
+            }
+        }
+    }
+}
+
+fn merge_array(target: &mut Array, value: Value) {
+    match value {
+        Value::Array(mut other) => target.append(&mut other),
+        value => {
+            if let Some(last) = target.last_mut() {
+                merge_value(&mut last.value, value);
+            }
+        }
+    }
+}
+
+/// Merge error.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("can't merge {other} into {target} at `{}`", Key::from_iter(.key.iter().cloned()))]
+pub struct MergeError {
+    /// The key of the conflicting value.
+    pub key: Vec<String>,
+    /// The type of the value merged into.
+    pub target: &'static str,
+    /// The type of the merged value.
+    pub other: &'static str,
+}
+
+impl MergeError {
+    fn new(target: &Value, other: &Value) -> Self {
+        Self {
+            key: Vec::new(),
+            target: target.type_str(),
+            other: other.type_str(),
+        }
+    }
+}
+
+impl Table {
+    /// Merges the other table into this one, as a layer over it.
+    ///
+    /// Returns the report of where every value of the merged table came from:
+    /// layer 0 is this table, layer 1 the other one.
+    pub fn merge_with(&mut self, other: Table, options: &MergeOptions) -> MergeReport {
+        let mut report = MergeReport::default();
+        self.merge_layer(other, options, &mut report);
+        report
+    }
+
+    /// Merges the other table into this one, as the next layer of the report.
+    ///
+    /// The first merge with a report records this table as layer 0.
+    pub fn merge_layer(&mut self, other: Table, options: &MergeOptions, report: &mut MergeReport) {
+        let mut path = Vec::new();
+        if report.layers == 0 {
+            for (segment, item) in self.iter() {
+                path.push(Step::Key(Segment::new(segment.clone())));
+                report.record(&mut path, &item.value, 0);
+                path.pop();
+            }
+        }
+        report.layers += 1;
+        let layer = report.layers;
+        merge_tables(self, other, options, &mut path, report, layer);
+    }
+}
+
+/// Merge options.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergeOptions {
+    /// The strategy of the keys without their own one.
+    pub strategy: Strategy,
+    /// The strategies by key.
+    pub strategies: IndexMap<Vec<String>, Strategy>,
+    pub comments: CommentPolicy,
+}
+
+impl MergeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the default strategy.
+    pub fn strategy(self, strategy: Strategy) -> Self {
+        Self { strategy, ..self }
+    }
+
+    /// Sets the strategy of the key.
+    ///
+    /// Keys inside arrays of tables are addressed without indices.
+    pub fn key(mut self, key: &[&str], strategy: Strategy) -> Self {
+        let key = key.iter().map(|&segment| segment.to_owned()).collect();
+        self.strategies.insert(key, strategy);
+        self
+    }
+
+    /// Sets the comment policy.
+    pub fn comments(self, comments: CommentPolicy) -> Self {
+        Self { comments, ..self }
+    }
+
+    /// Returns the strategy of the path, whose indices are skipped.
+    fn strategy_of(&self, path: &[Step]) -> &Strategy {
+        let key = path.iter().filter_map(|step| match step {
+            Step::Key(segment) => Some(&**segment),
+            Step::Index(_) => None,
+        });
+        self.strategies
+            .iter()
+            .find(|(k, _)| k.iter().map(String::as_str).eq(key.clone()))
+            .map_or(&self.strategy, |(_, strategy)| strategy)
+    }
+}
+
+/// Merge strategy.
+///
+/// A strategy applies when a key is in both tables and falls back to
+/// `Replace` for values it can't combine.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Strategy {
+    /// Replaces the target value with the source one.
+    Replace,
+    /// Merges tables key by key.
+    #[default]
+    DeepMerge,
+    /// Appends the source array to the target one.
+    Append,
+    /// Deep merges the tables of the arrays that have the same value at the
+    /// key, and appends the rest.
+    UnionBy(String),
+    /// Keeps the target value along with its comments.
+    KeepLeft,
+}
+
+/// Comment policy for the keys in both tables, but the kept left ones.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CommentPolicy {
+    KeepTarget,
+    KeepSource,
+    #[default]
+    Concatenate,
+}
+
+impl CommentPolicy {
+    fn apply(self, target: &mut Comments, source: &mut Comments) {
+        match self {
+            Self::KeepTarget => {}
+            Self::KeepSource => *target = take(source),
+            Self::Concatenate => target.append(source),
+        }
+    }
+}
+
+/// Merge report.
+///
+/// The origin of every value of the merged table that is neither a table nor
+/// an array of tables, whose elements are reported instead.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergeReport {
+    pub keys: IndexMap<Path<'static>, Origin>,
+    /// The number of layers merged over layer 0.
+    layers: usize,
+}
+
+impl MergeReport {
+    /// Returns the origin of the value at the path, like `servers[0].host`.
+    pub fn origin(&self, path: &str) -> Option<&Origin> {
+        let steps = Path::parse(path)
+            .ok()?
+            .0
+            .into_iter()
+            .map(|step| match step {
+                Step::Key(segment) => Step::Key(Segment::new(segment.to_string())),
+                Step::Index(index) => Step::Index(index),
+            });
+        self.keys.get(&Path(steps.collect()))
+    }
+
+    fn record(&mut self, path: &mut Vec<Step<'static>>, value: &Value, layer: usize) {
         match value {
-            Value::Array(mut other) => {
-                self.append(&mut other);
+            Value::Table(table) => {
+                for (segment, item) in table.iter() {
+                    path.push(Step::Key(Segment::new(segment.clone())));
+                    self.record(path, &item.value, layer);
+                    path.pop();
+                }
+            }
+            Value::Array(array) if is_array_of_tables(array) => {
+                for (index, item) in array.iter().enumerate() {
+                    path.push(Step::Index(index));
+                    self.record(path, &item.value, layer);
+                    path.pop();
+                }
             }
-            _ => panic!("Can't merge an array value with a not-array value."),
+            _ => {
+                self.keys.insert(Path(path.clone()), Origin::Layer(layer));
+            }
+        }
+    }
+
+    /// Forgets the origins of the value at the path and of its children.
+    fn forget(&mut self, path: &[Step<'static>]) {
+        self.keys.retain(|key, _| !key.starts_with(path));
+    }
+
+    /// Adds the layer to the origin of the array at the path, appended to.
+    ///
+    /// The elements appended to an array of tables are reported one by one.
+    fn append(&mut self, path: &mut Vec<Step<'static>>, array: &Array, from: usize, layer: usize) {
+        if is_array_of_tables(array) {
+            self.keys.shift_remove(&Path(path.clone()));
+            for (index, item) in array.iter().enumerate().skip(from) {
+                path.push(Step::Index(index));
+                self.record(path, &item.value, layer);
+                path.pop();
+            }
+        } else {
+            let origin = match self.keys.get(&Path(path.clone())) {
+                Some(Origin::Layer(first)) => Origin::Layers(vec![*first, layer]),
+                Some(Origin::Layers(layers)) => Origin::Layers([&layers[..], &[layer]].concat()),
+                None => Origin::Layer(layer),
+            };
+            self.forget(path);
+            self.keys.insert(Path(path.clone()), origin);
         }
     }
 }
 
+/// Origin of a merged value.
+///
+/// Layer 0 is the table merged into, each merged table is the next layer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Origin {
+    /// A single layer.
+    Layer(usize),
+    /// Several layers, e.g. appended arrays.
+    Layers(Vec<usize>),
+}
+
+fn merge_tables(
+    target: &mut Table,
+    source: Table,
+    options: &MergeOptions,
+    path: &mut Vec<Step<'static>>,
+    report: &mut MergeReport,
+    layer: usize,
+) {
+    for (segment, source) in source {
+        path.push(Step::Key(Segment::new(segment.clone())));
+        match target.get_mut(&segment) {
+            Some(target) => merge_items(target, source, options, path, report, layer),
+            None => {
+                report.record(path, &source.value, layer);
+                target.insert(segment, source);
+            }
+        }
+        path.pop();
+    }
+}
+
+fn merge_items(
+    target: &mut Item,
+    mut source: Item,
+    options: &MergeOptions,
+    path: &mut Vec<Step<'static>>,
+    report: &mut MergeReport,
+    layer: usize,
+) {
+    let strategy = options.strategy_of(path);
+    if let Strategy::KeepLeft = strategy {
+        return;
+    }
+    options
+        .comments
+        .apply(&mut target.comments, &mut source.comments);
+    match (strategy, &mut target.value, source.value) {
+        (Strategy::DeepMerge, Value::Table(target), Value::Table(source)) => {
+            merge_tables(target, source, options, path, report, layer)
+        }
+        (Strategy::Append, Value::Array(target), Value::Array(mut source)) => {
+            let from = target.len();
+            target.append(&mut source);
+            report.append(path, target, from, layer);
+        }
+        (Strategy::UnionBy(key), Value::Array(target), Value::Array(source)) => {
+            let from = target.len();
+            for source in source {
+                let index = target.iter().position(|target| {
+                    match (field(target, key), field(&source, key)) {
+                        (Some(target), Some(source)) => same(target, source),
+                        _ => false,
+                    }
+                });
+                match index {
+                    Some(index) => {
+                        path.push(Step::Index(index));
+                        merge_elements(&mut target[index], source, options, path, report, layer);
+                        path.pop();
+                    }
+                    None => target.push(source),
+                }
+            }
+            report.append(path, target, from, layer);
+        }
+        (_, target, source) => {
+            report.forget(path);
+            report.record(path, &source, layer);
+            *target = source;
+        }
+    }
+}
+
+/// Deep merges the tables of arrays of tables.
+fn merge_elements(
+    target: &mut Item,
+    mut source: Item,
+    options: &MergeOptions,
+    path: &mut Vec<Step<'static>>,
+    report: &mut MergeReport,
+    layer: usize,
+) {
+    options
+        .comments
+        .apply(&mut target.comments, &mut source.comments);
+    if let (Value::Table(target), Value::Table(source)) = (&mut target.value, source.value) {
+        merge_tables(target, source, options, path, report, layer);
+    }
+}
+
+/// Tests whether the array is a non-empty array of tables.
+fn is_array_of_tables(array: &Array) -> bool {
+    !array.is_empty() && array.iter().all(|item| item.is_table())
+}
+
+/// Returns the value at the key of the item if it is a table.
+fn field<'a>(item: &'a Item, key: &str) -> Option<&'a Value> {
+    Some(&item.value.as_table()?.get(key)?.value)
+}
+
+/// Tests whether the values are the same, regardless of their spelling.
+fn same(left: &Value, right: &Value) -> bool {
+    match (left.as_string(), right.as_string()) {
+        (Some(left), Some(right)) => **left == **right,
+        _ => left == right,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use indexmap::indexmap;
-    use std::iter::FromIterator;
 
     mod primitive {
         use super::*;
 
         #[test]
-        #[should_panic(expected = "Can't merge a primitive value.")]
         fn primitive() {
             let mut target = Value::from_iter(indexmap! {
                 "a" => Value::from(true),
@@ -62,11 +466,17 @@ mod test {
             let source = Value::from_iter(indexmap! {
                 "a" => Value::from(false),
             });
-            target.merge(source);
+            assert_eq!(
+                target.merge(source),
+                Err(MergeError {
+                    key: vec!["a".to_owned()],
+                    target: "boolean",
+                    other: "boolean",
+                }),
+            );
         }
 
         #[test]
-        #[should_panic(expected = "Can't merge a primitive value.")]
         fn array() {
             let mut target = Value::from_iter(indexmap! {
                 "a" => Value::from(true),
@@ -74,11 +484,17 @@ mod test {
             let source = Value::from_iter(indexmap! {
                 "a" => Value::from_iter(vec![Value::from(true)]),
             });
-            target.merge(source);
+            assert_eq!(
+                target.merge(source),
+                Err(MergeError {
+                    key: vec!["a".to_owned()],
+                    target: "boolean",
+                    other: "array",
+                }),
+            );
         }
 
         #[test]
-        #[should_panic(expected = "Can't merge a primitive value.")]
         fn table() {
             let mut target = Value::from_iter(indexmap! {
                 "a" => Value::from(true),
@@ -88,7 +504,14 @@ mod test {
                     "b" => Value::from(true),
                 }),
             });
-            target.merge(source);
+            assert_eq!(
+                target.merge(source),
+                Err(MergeError {
+                    key: vec!["a".to_owned()],
+                    target: "boolean",
+                    other: "table",
+                }),
+            );
         }
     }
 
@@ -96,7 +519,6 @@ mod test {
         use super::*;
 
         #[test]
-        #[should_panic(expected = "Can't merge a table value with a not-table value.")]
         fn primitive() {
             let mut target = Value::from_iter(indexmap! {
                 "a" => Value::from_iter(indexmap! {
@@ -106,11 +528,17 @@ mod test {
             let source = Value::from_iter(indexmap! {
                 "a" => Value::from(true),
             });
-            target.merge(source);
+            assert_eq!(
+                target.merge(source),
+                Err(MergeError {
+                    key: vec!["a".to_owned()],
+                    target: "table",
+                    other: "boolean",
+                }),
+            );
         }
 
         #[test]
-        #[should_panic(expected = "Can't merge a table value with a not-table value.")]
         fn array() {
             let mut target = Value::from_iter(indexmap! {
                 "a" => Value::from_iter(indexmap! {
@@ -120,7 +548,14 @@ mod test {
             let source = Value::from_iter(indexmap! {
                 "a" => Value::from_iter(vec![Value::from(true)]),
             });
-            target.merge(source);
+            assert_eq!(
+                target.merge(source),
+                Err(MergeError {
+                    key: vec!["a".to_owned()],
+                    target: "table",
+                    other: "array",
+                }),
+            );
         }
 
         #[test]
@@ -135,7 +570,7 @@ mod test {
                     "c" => Value::from(true),
                 }),
             });
-            target.merge(source);
+            target.merge(source).unwrap();
             assert_eq!(
                 target,
                 Value::from_iter(indexmap! {
@@ -148,11 +583,128 @@ mod test {
         }
     }
 
+    #[test]
+    fn nested() {
+        let mut target = Value::from_iter(indexmap! {
+            "a" => Value::from_iter(indexmap! {
+                "b c" => Value::from(1),
+            }),
+        });
+        let source = Value::from_iter(indexmap! {
+            "a" => Value::from_iter(indexmap! {
+                "a" => Value::from(2),
+                "b c" => Value::from_iter(indexmap! {
+                    "d" => Value::from(true),
+                }),
+            }),
+        });
+        // Nothing is merged, the key before the conflict included.
+        let unchanged = target.clone();
+        let error = target.merge(source).unwrap_err();
+        assert_eq!(target, unchanged);
+        assert_eq!(error.key, vec!["a".to_owned(), "b c".to_owned()]);
+        assert_eq!(
+            error.to_string(),
+            "can't merge table into integer at `a.'b c'`",
+        );
+    }
+
+    mod merge_with {
+        use super::*;
+        use crate::{comment::Comment, parse};
+
+        #[test]
+        fn layers() {
+            let mut config = parse(
+                r#"name = "default"
+ports = [80]
+[log]
+level = "info"
+file = "/var/log/app.log"
+[[servers]]
+name = "a"
+ip = "10.0.0.1"
+"#,
+            )
+            .unwrap();
+            let site = parse(
+                r#"ports = [443]
+[log]
+level = "warn"
+[[servers]]
+name = 'a'
+ip = "10.0.0.2"
+[[servers]]
+name = "b"
+"#,
+            )
+            .unwrap();
+            let user = parse("name = \"user\"\nlog = { level = \"debug\" }").unwrap();
+            let options = MergeOptions::new()
+                .key(&["ports"], Strategy::Append)
+                .key(&["servers"], Strategy::UnionBy("name".to_owned()))
+                .key(&["servers", "ip"], Strategy::KeepLeft);
+            let mut report = config.merge_with(site, &options);
+            assert_eq!(report.origin("name"), Some(&Origin::Layer(0)));
+            assert_eq!(report.origin("ports"), Some(&Origin::Layers(vec![0, 1])));
+            assert_eq!(report.origin("log.level"), Some(&Origin::Layer(1)));
+            assert_eq!(report.origin("log.file"), Some(&Origin::Layer(0)));
+            assert_eq!(report.origin("log"), None);
+            assert_eq!(report.origin("servers"), None);
+            assert_eq!(report.origin("servers[0].name"), Some(&Origin::Layer(1)));
+            assert_eq!(report.origin("servers[0].ip"), Some(&Origin::Layer(0)));
+            assert_eq!(report.origin("servers[1].name"), Some(&Origin::Layer(1)));
+            config.merge_layer(user, &options.strategy(Strategy::Replace), &mut report);
+            assert_eq!(report.origin("name"), Some(&Origin::Layer(2)));
+            assert_eq!(report.origin("ports"), Some(&Origin::Layers(vec![0, 1])));
+            assert_eq!(report.origin("log.level"), Some(&Origin::Layer(2)));
+            assert_eq!(report.origin("log.file"), None);
+            assert_eq!(report.origin("servers[0].ip"), Some(&Origin::Layer(0)));
+            assert_eq!(
+                config,
+                parse(
+                    r#"name = "user"
+ports = [80, 443]
+log = { level = "debug" }
+[[servers]]
+name = 'a'
+ip = "10.0.0.1"
+[[servers]]
+name = "b"
+"#,
+                )
+                .unwrap(),
+            );
+        }
+
+        #[test]
+        fn comments() {
+            let target = parse("# Target.\na = 1").unwrap();
+            let source = parse("# Source.\na = 2").unwrap();
+            let comments = |policy| {
+                let mut target = target.clone();
+                let options = MergeOptions::new().comments(policy);
+                target.merge_with(source.clone(), &options);
+                target["a"].comments.clone()
+            };
+            let pre = |comment: &str| Comment::Pre(comment.to_owned());
+            assert_eq!(*comments(CommentPolicy::KeepTarget), vec![pre(" Target.")]);
+            assert_eq!(*comments(CommentPolicy::KeepSource), vec![pre(" Source.")]);
+            assert_eq!(
+                *comments(CommentPolicy::Concatenate),
+                vec![pre(" Target."), pre(" Source.")],
+            );
+            let mut target = target.clone();
+            let options = MergeOptions::new().key(&["a"], Strategy::KeepLeft);
+            target.merge_with(source, &options);
+            assert_eq!(*target["a"].comments, vec![pre(" Target.")]);
+        }
+    }
+
     mod array {
         use super::*;
 
         #[test]
-        #[should_panic(expected = "Can't merge an array value with a not-array value.")]
         fn primitive() {
             let mut target = Value::from_iter(indexmap! {
                 "a" => Value::from_iter(vec![Value::from(true)]),
@@ -160,7 +712,14 @@ mod test {
             let source = Value::from_iter(indexmap! {
                 "a" => Value::from(false),
             });
-            target.merge(source);
+            assert_eq!(
+                target.merge(source),
+                Err(MergeError {
+                    key: vec!["a".to_owned()],
+                    target: "array",
+                    other: "boolean",
+                }),
+            );
         }
 
         #[test]
@@ -171,7 +730,7 @@ mod test {
             let source = Value::from_iter(indexmap! {
                 "a" => Value::from_iter(vec![Value::from(true)]),
             });
-            target.merge(source);
+            target.merge(source).unwrap();
             assert_eq!(
                 target,
                 Value::from_iter(indexmap! {
@@ -181,7 +740,6 @@ mod test {
         }
 
         #[test]
-        #[should_panic(expected = "Can't merge an array value with a not-array value.")]
         fn table() {
             let mut target = Value::from_iter(indexmap! {
                 "a" => Value::from_iter(vec![Value::from(true)]),
@@ -191,7 +749,14 @@ mod test {
                     "b" => Value::from(true),
                 }),
             });
-            target.merge(source);
+            assert_eq!(
+                target.merge(source),
+                Err(MergeError {
+                    key: vec!["a".to_owned()],
+                    target: "array",
+                    other: "table",
+                }),
+            );
         }
     }
 }
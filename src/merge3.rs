@@ -0,0 +1,269 @@
+use crate::{
+    comment::Comments,
+    diff::equal,
+    format::Inline,
+    key::Segment,
+    path::{Path, Step},
+    value::{Item, Table, Value},
+};
+use std::fmt::{self, Display, Formatter};
+
+/// Merges the changes from the base table to ours and to theirs.
+///
+/// Tables are merged key by key, anything else is merged as a whole. A value
+/// changed on one side only takes that change, a value changed on both sides
+/// to different values is a conflict and keeps our side. Comments are merged
+/// the same way, except that comments changed on both sides are concatenated.
+///
+/// Returns the merged table and its conflicts.
+pub fn merge3(base: &Table, ours: &Table, theirs: &Table) -> (Table, Vec<Conflict>) {
+    let mut conflicts = Vec::new();
+    let table = tables(base, ours, theirs, &mut Vec::new(), &mut conflicts);
+    (table, conflicts)
+}
+
+/// Conflict.
+///
+/// Displays as git conflict markers around the conflicting key, a side
+/// without the key is empty.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    /// The full path of the conflicting value.
+    pub path: Path<'static>,
+    pub base: Option<Value>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+}
+
+impl Conflict {
+    fn new(
+        path: &[Step<'static>],
+        base: Option<&Value>,
+        ours: Option<&Value>,
+        theirs: Option<&Value>,
+    ) -> Self {
+        Self {
+            path: Path(path.to_vec()),
+            base: base.cloned(),
+            ours: ours.cloned(),
+            theirs: theirs.cloned(),
+        }
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let side = |f: &mut Formatter, value: &Option<Value>| match value {
+            Some(value) => writeln!(f, "{} = {}", self.path, Inline::new(value)),
+            None => Ok(()),
+        };
+        writeln!(f, "<<<<<<< ours")?;
+        side(f, &self.ours)?;
+        writeln!(f, "||||||| base")?;
+        side(f, &self.base)?;
+        writeln!(f, "=======")?;
+        side(f, &self.theirs)?;
+        writeln!(f, ">>>>>>> theirs")
+    }
+}
+
+fn tables(
+    base: &Table,
+    ours: &Table,
+    theirs: &Table,
+    path: &mut Vec<Step<'static>>,
+    conflicts: &mut Vec<Conflict>,
+) -> Table {
+    let mut merged = Table::new();
+    // Our keys keep their order, their new keys follow.
+    let keys = ours
+        .keys()
+        .chain(theirs.keys().filter(|key| !ours.contains_key(*key)));
+    for key in keys {
+        path.push(Step::Key(Segment::new(key.clone())));
+        let item = items(
+            base.get(key),
+            ours.get(key),
+            theirs.get(key),
+            path,
+            conflicts,
+        );
+        if let Some(item) = item {
+            merged.insert(key.clone(), item);
+        }
+        path.pop();
+    }
+    merged
+}
+
+fn items(
+    base: Option<&Item>,
+    ours: Option<&Item>,
+    theirs: Option<&Item>,
+    path: &mut Vec<Step<'static>>,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Item> {
+    let base_value = base.map(|base| &base.value);
+    match (ours, theirs) {
+        (Some(ours), Some(theirs)) => {
+            let base_comments = base.map(|base| &base.comments);
+            let comments = merge_comments(base_comments, &ours.comments, &theirs.comments);
+            let value = match (&ours.value, &theirs.value) {
+                (Value::Table(ours), Value::Table(theirs)) => {
+                    let empty = Table::new();
+                    let base = base_value.and_then(Value::as_table).unwrap_or(&empty);
+                    Value::Table(tables(base, ours, theirs, path, conflicts))
+                }
+                (ours, theirs) => match base_value {
+                    _ if equal(ours, theirs) => ours.clone(),
+                    Some(base) if equal(base, ours) => theirs.clone(),
+                    Some(base) if equal(base, theirs) => ours.clone(),
+                    base => {
+                        conflicts.push(Conflict::new(path, base, Some(ours), Some(theirs)));
+                        ours.clone()
+                    }
+                },
+            };
+            Some(Item {
+                comments,
+                value,
+                key_style: ours.key_style,
+            })
+        }
+        // Deleted by them.
+        (Some(ours), None) => match base_value {
+            None => Some(ours.clone()),
+            Some(base) if equal(base, &ours.value) => None,
+            Some(base) => {
+                conflicts.push(Conflict::new(path, Some(base), Some(&ours.value), None));
+                Some(ours.clone())
+            }
+        },
+        // Deleted by us.
+        (None, Some(theirs)) => match base_value {
+            None => Some(theirs.clone()),
+            Some(base) if equal(base, &theirs.value) => None,
+            Some(base) => {
+                conflicts.push(Conflict::new(path, Some(base), None, Some(&theirs.value)));
+                None
+            }
+        },
+        (None, None) => None,
+    }
+}
+
+fn merge_comments(base: Option<&Comments>, ours: &Comments, theirs: &Comments) -> Comments {
+    if ours == theirs || base == Some(theirs) {
+        ours.clone()
+    } else if base == Some(ours) {
+        theirs.clone()
+    } else {
+        let mut comments = ours.clone();
+        for comment in theirs.iter() {
+            if !ours.contains(comment) {
+                comments.push(comment.clone());
+            }
+        }
+        comments
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+
+    const BASE: &str = r#"# Server.
+host = "localhost"
+port = 8080
+timeout = 30
+
+[log]
+level = "info"
+file = "app.log"
+"#;
+
+    #[test]
+    fn non_overlapping() {
+        let ours = r#"# Server.
+# Changed by the user.
+host = "example.com"
+port = 8080
+timeout = 30
+
+[log]
+level = "debug" # Verbose.
+file = "app.log"
+"#;
+        let theirs = r#"# Server.
+host = "localhost"
+port = 8080
+timeout = 60
+workers = 4
+
+[log]
+level = "info"
+"#;
+        let (merged, conflicts) = merge3(
+            &parse(BASE).unwrap(),
+            &parse(ours).unwrap(),
+            &parse(theirs).unwrap(),
+        );
+        assert!(conflicts.is_empty());
+        let expected = r#"# Server.
+# Changed by the user.
+host = "example.com"
+port = 8080
+timeout = 60
+workers = 4
+
+[log]
+level = "debug" # Verbose.
+"#;
+        assert_eq!(merged, parse(expected).unwrap());
+    }
+
+    #[test]
+    fn comments() {
+        let ours = "# Ours.\nhost = \"localhost\"\nport = 8080 # Ours.\ntimeout = 30\n";
+        let theirs = "# Theirs.\nhost = \"localhost\"\nport = 8080\ntimeout = 30 # Theirs.\n";
+        let (merged, conflicts) = merge3(
+            &parse(BASE).unwrap(),
+            &parse(ours).unwrap(),
+            &parse(theirs).unwrap(),
+        );
+        assert!(conflicts.is_empty());
+        let expected = "# Ours.\n# Theirs.\nhost = \"localhost\"\nport = 8080 # Ours.\n\
+                        timeout = 30 # Theirs.\n";
+        assert_eq!(merged, parse(expected).unwrap());
+    }
+
+    #[test]
+    fn conflicts() {
+        let ours = "host = \"localhost\"\nport = 8081\ntimeout = 30\n";
+        let theirs = "host = \"localhost\"\nport = 9090\n[log]\nlevel = \"warn\"\n";
+        let (merged, conflicts) = merge3(
+            &parse(BASE).unwrap(),
+            &parse(ours).unwrap(),
+            &parse(theirs).unwrap(),
+        );
+        let paths: Vec<_> = conflicts.iter().map(|c| c.path.to_string()).collect();
+        assert_eq!(paths, ["port", "log"]);
+        assert_eq!(
+            conflicts[0].to_string(),
+            "<<<<<<< ours\nport = 8081\n||||||| base\nport = 8080\n=======\nport = 9090\n\
+             >>>>>>> theirs\n",
+        );
+        assert_eq!(conflicts[1].ours, None);
+        assert_eq!(
+            conflicts[1].to_string(),
+            "<<<<<<< ours\n||||||| base\nlog = { level = \"info\", file = \"app.log\" }\n\
+             =======\nlog = { level = \"warn\" }\n>>>>>>> theirs\n",
+        );
+        // Unchanged by us and deleted by them.
+        assert_eq!(
+            merged,
+            parse("host = \"localhost\"\nport = 8081\n").unwrap()
+        );
+    }
+}
@@ -0,0 +1,365 @@
+use crate::{
+    comment::Comments,
+    diff::{equal, ChangeKind, Diff},
+    format::Inline,
+    parse,
+    path::{Path, PathError, Step},
+    value::{Item, Table, Value},
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use thiserror::Error;
+
+/// Patch.
+///
+/// The changes of a diff, to be applied to another table. Values are kept as
+/// their inline TOML, so that a patch keeps their representation when it is
+/// serialized to any format.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Patch {
+    pub operations: Vec<Operation>,
+}
+
+impl Patch {
+    /// Applies the patch to the table, keeping the comments and formatting of
+    /// the values it does not change.
+    ///
+    /// Every operation is checked against the value the table has at its
+    /// path. If any of them conflicts, the table is left unchanged.
+    pub fn apply(&self, table: &mut Table) -> Result<(), PatchError> {
+        let mut patched = table.clone();
+        let mut conflicts = Vec::new();
+        for operation in &self.operations {
+            if let Err(conflict) = operation.apply(&mut patched)? {
+                conflicts.push(conflict);
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(PatchError::Conflicts(conflicts));
+        }
+        *table = patched;
+        Ok(())
+    }
+}
+
+impl From<&Diff> for Patch {
+    fn from(from: &Diff) -> Self {
+        let inline = |value: &Value| Inline::new(value).to_string();
+        let operations = from
+            .iter()
+            .map(|change| {
+                let path = change.path.to_string();
+                match &change.kind {
+                    ChangeKind::Added(value) => Operation::Add {
+                        path,
+                        value: inline(value),
+                    },
+                    ChangeKind::Removed(old) => Operation::Remove {
+                        path,
+                        old: inline(old),
+                    },
+                    ChangeKind::Changed(old, new) | ChangeKind::Representation(old, new) => {
+                        Operation::Replace {
+                            path,
+                            old: inline(old),
+                            new: inline(new),
+                        }
+                    }
+                    ChangeKind::Inserted(value) => Operation::Insert {
+                        path,
+                        value: inline(value),
+                    },
+                    ChangeKind::Deleted(old) => Operation::Delete {
+                        path,
+                        old: inline(old),
+                    },
+                    ChangeKind::Comments(old, new) => Operation::Comments {
+                        path,
+                        old: old.clone(),
+                        new: new.clone(),
+                    },
+                }
+            })
+            .collect();
+        Self { operations }
+    }
+}
+
+impl From<Diff> for Patch {
+    fn from(from: Diff) -> Self {
+        Self::from(&from)
+    }
+}
+
+/// Patch operation.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    /// Adds the key.
+    Add { path: String, value: String },
+    /// Removes the key.
+    Remove { path: String, old: String },
+    /// Replaces the value.
+    Replace {
+        path: String,
+        old: String,
+        new: String,
+    },
+    /// Inserts the array element.
+    Insert { path: String, value: String },
+    /// Removes the array element.
+    Delete { path: String, old: String },
+    /// Replaces the comments of the value.
+    Comments {
+        path: String,
+        old: Comments,
+        new: Comments,
+    },
+}
+
+impl Operation {
+    /// Returns the path of the operation.
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Add { path, .. }
+            | Self::Remove { path, .. }
+            | Self::Replace { path, .. }
+            | Self::Insert { path, .. }
+            | Self::Delete { path, .. }
+            | Self::Comments { path, .. } => path,
+        }
+    }
+
+    fn apply(&self, table: &mut Table) -> Result<Result<(), Conflict>, PatchError> {
+        let path = self.path();
+        let found = table.get_path(path).ok().map(|item| item.value.clone());
+        let conflict = |expected: Option<&str>| Conflict {
+            path: path.to_owned(),
+            expected: expected.map(String::from),
+            found: found.as_ref().map(|value| Inline::new(value).to_string()),
+        };
+        match self {
+            Self::Add { value, .. } => {
+                if found.is_some() {
+                    return Ok(Err(conflict(None)));
+                }
+                table.insert_path(path, value_of(value)?)?;
+            }
+            Self::Remove { old, .. } | Self::Delete { old, .. } => {
+                let expected = value_of(old)?;
+                if !matches!(&found, Some(value) if equal(value, &expected)) {
+                    return Ok(Err(conflict(Some(old))));
+                }
+                table.remove_path(path)?;
+            }
+            Self::Replace { old, new, .. } => {
+                let expected = value_of(old)?;
+                if !matches!(&found, Some(value) if equal(value, &expected)) {
+                    return Ok(Err(conflict(Some(old))));
+                }
+                table.get_path_mut(path)?.value = value_of(new)?;
+            }
+            Self::Insert { value, .. } => {
+                let parsed = Path::parse(path)?;
+                let (last, parent) = parsed.split_last().expect("non-empty path");
+                let parent = Path(parent.to_vec()).to_string();
+                let array = match table.get_path_mut(&parent) {
+                    Ok(Item {
+                        value: Value::Array(array),
+                        ..
+                    }) => array,
+                    _ => return Ok(Err(conflict(None))),
+                };
+                match *last {
+                    Step::Index(index) if index <= array.len() => {
+                        array.insert(index, Item::from(value_of(value)?));
+                    }
+                    _ => return Ok(Err(conflict(None))),
+                }
+            }
+            Self::Comments { old, new, .. } => match table.get_path_mut(path) {
+                Ok(item) if item.comments == *old => item.comments = new.clone(),
+                item => {
+                    // The comments are compared rather than the values.
+                    let found = item.ok().map(|item| &item.comments);
+                    return Ok(Err(Conflict {
+                        path: path.to_owned(),
+                        expected: joined(old),
+                        found: found.and_then(joined),
+                    }));
+                }
+            },
+        }
+        Ok(Ok(()))
+    }
+}
+
+/// Conflict.
+///
+/// The value at the path, or its comments for a comments operation, is not
+/// the one the operation expects.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Conflict {
+    pub path: String,
+    /// The expected inline value or comments, none if nothing is expected.
+    pub expected: Option<String>,
+    /// The found inline value or comments, none if there is nothing.
+    pub found: Option<String>,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let or_nothing = |value: &Option<String>| {
+            value
+                .as_ref()
+                .map_or_else(|| "nothing".to_owned(), |value| format!("`{}`", value))
+        };
+        write!(
+            f,
+            "conflict at `{}`: expected {}, found {}",
+            self.path,
+            or_nothing(&self.expected),
+            or_nothing(&self.found),
+        )
+    }
+}
+
+/// Patch error.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum PatchError {
+    #[error("{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"))]
+    Conflicts(Vec<Conflict>),
+    #[error(transparent)]
+    Path(#[from] PathError),
+    #[error("invalid value `{0}`")]
+    Value(String),
+}
+
+/// Joins the comments on a line, none if there are no comments.
+fn joined(comments: &Comments) -> Option<String> {
+    if comments.is_empty() {
+        return None;
+    }
+    let comments = comments.iter().map(ToString::to_string).collect::<Vec<_>>();
+    Some(comments.join(" "))
+}
+
+/// Parses the inline value.
+fn value_of(inline: &str) -> Result<Value, PatchError> {
+    let mut table =
+        parse(&format!("value = {}", inline)).map_err(|_| PatchError::Value(inline.to_owned()))?;
+    table
+        .remove("value")
+        .map(|item| item.value)
+        .ok_or_else(|| PatchError::Value(inline.to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{diff, from_str, to_string};
+
+    const OLD: &str = r#"a = 1
+b = 0xff
+# c
+c = [1, 2, 3]
+
+[d]
+e = 'f'
+"#;
+
+    const NEW: &str = r#"a = 2
+b = 255
+# C
+c = [0, 1, 3]
+
+[d]
+e = 'f'
+g = true
+"#;
+
+    fn patch() -> Patch {
+        Patch::from(diff(&parse(OLD).unwrap(), &parse(NEW).unwrap()))
+    }
+
+    #[test]
+    fn apply() {
+        let mut table = parse(OLD).unwrap();
+        patch().apply(&mut table).unwrap();
+        assert_eq!(table, parse(NEW).unwrap());
+    }
+
+    #[test]
+    fn apply_modified() {
+        let mut table =
+            parse("# user\na = 1 # user\nb = 255\nc = [1, 2, 3]\nh = 'i'\n[d]\ne = 'f'\n").unwrap();
+        let mut patch = patch();
+        // The comments of `c` are changed by the user too.
+        patch
+            .operations
+            .retain(|operation| !matches!(operation, Operation::Comments { .. }));
+        patch.apply(&mut table).unwrap();
+        assert_eq!(
+            table["a"].comments,
+            parse("# user\na = 1 # user").unwrap()["a"].comments
+        );
+        assert_eq!(table.get_path("a").unwrap().value, Value::from(2));
+        assert_eq!(
+            Inline::new(&table.get_path("b").unwrap().value).to_string(),
+            "255"
+        );
+        assert_eq!(
+            table.get_path("c").unwrap().value,
+            parse("c = [0, 1, 3]").unwrap()["c"].value
+        );
+        assert_eq!(table.get_path("d.g").unwrap().value, Value::from(true));
+        assert!(table.get_path("h").is_ok());
+    }
+
+    #[test]
+    fn conflict() {
+        let input = "a = 3\nb = 0xff\n# d\nc = [1, 2, 3]\n[d]\ne = 'f'\ng = false\n";
+        let mut table = parse(input).unwrap();
+        let error = patch().apply(&mut table).unwrap_err();
+        assert_eq!(
+            error,
+            PatchError::Conflicts(vec![
+                Conflict {
+                    path: "a".to_owned(),
+                    expected: Some("1".to_owned()),
+                    found: Some("3".to_owned()),
+                },
+                Conflict {
+                    path: "c".to_owned(),
+                    expected: Some("# c".to_owned()),
+                    found: Some("# d".to_owned()),
+                },
+                Conflict {
+                    path: "d.g".to_owned(),
+                    expected: None,
+                    found: Some("false".to_owned()),
+                },
+            ]),
+        );
+        assert_eq!(
+            error.to_string().lines().next(),
+            Some("conflict at `a`: expected `1`, found `3`"),
+        );
+        assert_eq!(
+            error.to_string().lines().nth(1),
+            Some("conflict at `c`: expected `# c`, found `# d`"),
+        );
+        assert_eq!(table, parse(input).unwrap());
+    }
+
+    #[test]
+    fn serialize() {
+        let patch = patch();
+        let toml = to_string(&patch).unwrap();
+        assert!(toml
+            .contains("[[operations]]\nop = 'replace'\npath = 'b'\nold = '0xff'\nnew = '255'\n"));
+        assert_eq!(from_str::<Patch>(&toml).unwrap(), patch);
+        let json = serde_json::to_string(&patch).unwrap();
+        assert_eq!(serde_json::from_str::<Patch>(&json).unwrap(), patch);
+    }
+}
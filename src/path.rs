@@ -0,0 +1,607 @@
+use crate::{
+    escape::{unescape, Mode},
+    key::Segment,
+    quotes::{Quoted, Quotes},
+    value::{Item, Table, Value},
+};
+use derive_more::Deref;
+use std::{
+    borrow::Cow,
+    fmt::{self, Display, Formatter},
+    mem::replace,
+};
+use thiserror::Error;
+
+impl Table {
+    /// Returns the item at the path.
+    ///
+    /// The path is a dotted key with array indices, like `servers[0].host`.
+    pub fn get_path(&self, path: &str) -> Result<&Item, PathError> {
+        let path = Path::parse(path)?;
+        let item = path.table(self, 0)?;
+        path.walk(item, 1)
+    }
+
+    /// Returns the mutable item at the path.
+    pub fn get_path_mut(&mut self, path: &str) -> Result<&mut Item, PathError> {
+        let path = Path::parse(path)?;
+        let item = path.table_mut(self, 0, false)?;
+        path.walk_mut(item, 1, path.len(), false)
+    }
+
+    /// Inserts the item at the path, creating the missing intermediate tables.
+    ///
+    /// Returns the replaced item if there is one. Nothing is created if the
+    /// item can't be inserted.
+    pub fn insert_path<T: Into<Item>>(
+        &mut self,
+        path: &str,
+        item: T,
+    ) -> Result<Option<Item>, PathError> {
+        let path = Path::parse(path)?;
+        let last = path.len() - 1;
+        if last == 0 {
+            return path.insert_table(self, last, item.into());
+        }
+        path.check_insert(path.lookup_table(self, 0)?)?;
+        let parent = path.table_mut(self, 0, true)?;
+        let parent = path.walk_mut(parent, 1, last, true)?;
+        path.insert(&mut parent.value, last, item.into())
+    }
+
+    /// Removes the item at the path.
+    pub fn remove_path(&mut self, path: &str) -> Result<Item, PathError> {
+        let path = Path::parse(path)?;
+        let last = path.len() - 1;
+        if last == 0 {
+            return path.remove_table(self, last);
+        }
+        let parent = path.table_mut(self, 0, false)?;
+        let parent = path.walk_mut(parent, 1, last, false)?;
+        path.remove(&mut parent.value, last)
+    }
+}
+
+impl Value {
+    /// Returns the item at the path.
+    ///
+    /// The path of an array value starts with an index, like `[0].host`.
+    pub fn get_path(&self, path: &str) -> Result<&Item, PathError> {
+        Path::parse(path)?.get(self)
+    }
+
+    /// Returns the mutable item at the path.
+    pub fn get_path_mut(&mut self, path: &str) -> Result<&mut Item, PathError> {
+        let path = Path::parse(path)?;
+        let item = path.value_mut(self, 0, false)?;
+        path.walk_mut(item, 1, path.len(), false)
+    }
+
+    /// Inserts the item at the path, creating the missing intermediate tables.
+    ///
+    /// Returns the replaced item if there is one. Nothing is created if the
+    /// item can't be inserted.
+    pub fn insert_path<T: Into<Item>>(
+        &mut self,
+        path: &str,
+        item: T,
+    ) -> Result<Option<Item>, PathError> {
+        let path = Path::parse(path)?;
+        let last = path.len() - 1;
+        if last == 0 {
+            return path.insert(self, last, item.into());
+        }
+        path.check_insert(path.lookup(self, 0)?)?;
+        let parent = path.value_mut(self, 0, true)?;
+        let parent = path.walk_mut(parent, 1, last, true)?;
+        path.insert(&mut parent.value, last, item.into())
+    }
+
+    /// Removes the item at the path.
+    pub fn remove_path(&mut self, path: &str) -> Result<Item, PathError> {
+        let path = Path::parse(path)?;
+        let last = path.len() - 1;
+        if last == 0 {
+            return path.remove(self, last);
+        }
+        let parent = path.value_mut(self, 0, false)?;
+        let parent = path.walk_mut(parent, 1, last, false)?;
+        path.remove(&mut parent.value, last)
+    }
+}
+
+/// Path.
+///
+/// A dotted key with array indices, like `servers[0]."host name"`. The key
+/// segments are bare or quoted, and the dots surrounded by whitespace, as in
+/// TOML keys.
+#[derive(Clone, Debug, Deref, Eq, Hash, PartialEq)]
+pub struct Path<'a>(pub(crate) Vec<Step<'a>>);
+
+impl<'a> Path<'a> {
+    /// Parses the path.
+    pub fn parse(input: &'a str) -> Result<Self, PathError> {
+        let error = |offset| PathError::Syntax(input.to_owned(), offset);
+        let mut steps = Vec::new();
+        let mut offset = 0;
+        while offset < input.len() {
+            let rest = &input[offset..];
+            if let Some(rest) = rest.strip_prefix('[') {
+                let end = rest.find(']').ok_or_else(|| error(input.len()))?;
+                let digits = &rest[..end];
+                if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(error(offset + 1));
+                }
+                let index = digits.parse().map_err(|_| error(offset + 1))?;
+                steps.push(Step::Index(index));
+                offset += end + 2;
+            } else {
+                // Key segments but the first one follow a dot.
+                if !steps.is_empty() {
+                    let dot = rest.trim_start_matches(is_whitespace);
+                    if !dot.starts_with('.') {
+                        return Err(error(offset + rest.len() - dot.len()));
+                    }
+                    offset += rest.len() - dot[1..].trim_start_matches(is_whitespace).len();
+                }
+                let (segment, len) =
+                    segment(&input[offset..]).map_err(|index| error(offset + index))?;
+                steps.push(Step::Key(segment));
+                offset += len;
+            }
+        }
+        if steps.is_empty() {
+            return Err(PathError::Empty);
+        }
+        Ok(Self(steps))
+    }
+}
+
+impl Path<'_> {
+    /// Returns the item at this path in the value.
+    pub(crate) fn get<'b>(&self, value: &'b Value) -> Result<&'b Item, PathError> {
+        let item = self.value(value, 0)?;
+        self.walk(item, 1)
+    }
+
+    /// Returns the path up to the step, including it.
+    fn prefix(&self, at: usize) -> String {
+        Path(self.0[..=at].to_vec()).to_string()
+    }
+
+    /// Returns the error of the value of the wrong type for the step.
+    fn mismatch(&self, at: usize, found: &'static str) -> PathError {
+        let prefix = if at == 0 {
+            String::new()
+        } else {
+            self.prefix(at - 1)
+        };
+        let expected = match self.0[at] {
+            Step::Key(_) => "table",
+            Step::Index(_) => "array",
+        };
+        PathError::Type(prefix, expected, found)
+    }
+
+    /// Returns the item of the step in the table, or `None` if it would be
+    /// created.
+    fn lookup_table<'b>(&self, table: &'b Table, at: usize) -> Result<Option<&'b Item>, PathError> {
+        match &self.0[at] {
+            Step::Key(key) => Ok(table.get(&**key)),
+            Step::Index(_) => Err(self.mismatch(at, "table")),
+        }
+    }
+
+    /// Returns the item of the step in the value, or `None` if it would be
+    /// created.
+    fn lookup<'b>(&self, value: &'b Value, at: usize) -> Result<Option<&'b Item>, PathError> {
+        match (&self.0[at], value) {
+            (Step::Key(_), Value::Table(table)) => self.lookup_table(table, at),
+            (&Step::Index(index), Value::Array(array)) if index <= array.len() => {
+                Ok(array.get(index))
+            }
+            (&Step::Index(_), Value::Array(array)) => {
+                Err(PathError::OutOfBounds(self.prefix(at), array.len()))
+            }
+            (_, value) => Err(self.mismatch(at, value.type_str())),
+        }
+    }
+
+    /// Checks the steps following the first one, whose item is given, before
+    /// any table is created for them.
+    fn check_insert(&self, mut item: Option<&Item>) -> Result<(), PathError> {
+        for at in 1..self.len() {
+            match item {
+                Some(parent) => item = self.lookup(&parent.value, at)?,
+                // Created tables have keys only.
+                None if matches!(self.0[at], Step::Index(_)) => {
+                    return Err(self.mismatch(at, "table"));
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn walk<'b>(&self, mut item: &'b Item, from: usize) -> Result<&'b Item, PathError> {
+        for at in from..self.len() {
+            item = self.value(&item.value, at)?;
+        }
+        Ok(item)
+    }
+
+    fn walk_mut<'b>(
+        &self,
+        mut item: &'b mut Item,
+        from: usize,
+        to: usize,
+        create: bool,
+    ) -> Result<&'b mut Item, PathError> {
+        for at in from..to {
+            item = self.value_mut(&mut item.value, at, create)?;
+        }
+        Ok(item)
+    }
+
+    fn table<'b>(&self, table: &'b Table, at: usize) -> Result<&'b Item, PathError> {
+        match &self.0[at] {
+            Step::Key(key) => table
+                .get(&**key)
+                .ok_or_else(|| PathError::NotFound(self.prefix(at))),
+            Step::Index(_) => Err(self.mismatch(at, "table")),
+        }
+    }
+
+    fn value<'b>(&self, value: &'b Value, at: usize) -> Result<&'b Item, PathError> {
+        match (&self.0[at], value) {
+            (Step::Key(_), Value::Table(table)) => self.table(table, at),
+            (&Step::Index(index), Value::Array(array)) => array
+                .get(index)
+                .ok_or_else(|| PathError::OutOfBounds(self.prefix(at), array.len())),
+            (_, value) => Err(self.mismatch(at, value.type_str())),
+        }
+    }
+
+    /// Creates the missing table of the key if `create` is set.
+    fn table_mut<'b>(
+        &self,
+        table: &'b mut Table,
+        at: usize,
+        create: bool,
+    ) -> Result<&'b mut Item, PathError> {
+        match &self.0[at] {
+            Step::Key(key) if create => Ok(table
+                .entry(String::from(&**key))
+                .or_insert_with(|| Item::from(Value::from(Table::new())))),
+            Step::Key(key) => table
+                .get_mut(&**key)
+                .ok_or_else(|| PathError::NotFound(self.prefix(at))),
+            Step::Index(_) => Err(self.mismatch(at, "table")),
+        }
+    }
+
+    /// Creates the missing table of the key, or pushes the table right after
+    /// the last element of the array, if `create` is set.
+    fn value_mut<'b>(
+        &self,
+        value: &'b mut Value,
+        at: usize,
+        create: bool,
+    ) -> Result<&'b mut Item, PathError> {
+        match (&self.0[at], value) {
+            (Step::Key(_), Value::Table(table)) => self.table_mut(table, at, create),
+            (&Step::Index(index), Value::Array(array)) => {
+                if create && index == array.len() {
+                    array.push(Item::from(Value::from(Table::new())));
+                }
+                let len = array.len();
+                array
+                    .get_mut(index)
+                    .ok_or_else(|| PathError::OutOfBounds(self.prefix(at), len))
+            }
+            (_, value) => Err(self.mismatch(at, value.type_str())),
+        }
+    }
+
+    fn insert_table(
+        &self,
+        table: &mut Table,
+        at: usize,
+        item: Item,
+    ) -> Result<Option<Item>, PathError> {
+        match &self.0[at] {
+            Step::Key(key) => Ok(table.insert(String::from(&**key), item)),
+            Step::Index(_) => Err(self.mismatch(at, "table")),
+        }
+    }
+
+    /// Replaces the element of the array, or pushes it right after the last
+    /// one.
+    fn insert(&self, value: &mut Value, at: usize, item: Item) -> Result<Option<Item>, PathError> {
+        match (&self.0[at], value) {
+            (Step::Key(_), Value::Table(table)) => self.insert_table(table, at, item),
+            (&Step::Index(index), Value::Array(array)) if index < array.len() => {
+                Ok(Some(replace(&mut array[index], item)))
+            }
+            (&Step::Index(index), Value::Array(array)) if index == array.len() => {
+                array.push(item);
+                Ok(None)
+            }
+            (&Step::Index(_), Value::Array(array)) => {
+                Err(PathError::OutOfBounds(self.prefix(at), array.len()))
+            }
+            (_, value) => Err(self.mismatch(at, value.type_str())),
+        }
+    }
+
+    fn remove_table(&self, table: &mut Table, at: usize) -> Result<Item, PathError> {
+        match &self.0[at] {
+            Step::Key(key) => table
+                .shift_remove(&**key)
+                .ok_or_else(|| PathError::NotFound(self.prefix(at))),
+            Step::Index(_) => Err(self.mismatch(at, "table")),
+        }
+    }
+
+    fn remove(&self, value: &mut Value, at: usize) -> Result<Item, PathError> {
+        match (&self.0[at], value) {
+            (Step::Key(_), Value::Table(table)) => self.remove_table(table, at),
+            (&Step::Index(index), Value::Array(array)) if index < array.len() => {
+                Ok(array.remove(index))
+            }
+            (&Step::Index(_), Value::Array(array)) => {
+                Err(PathError::OutOfBounds(self.prefix(at), array.len()))
+            }
+            (_, value) => Err(self.mismatch(at, value.type_str())),
+        }
+    }
+}
+
+impl Display for Path<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (index, step) in self.iter().enumerate() {
+            match step {
+                Step::Key(key) if index == 0 => write!(f, "{}", key)?,
+                Step::Key(key) => write!(f, ".{}", key)?,
+                Step::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Path step.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Step<'a> {
+    Key(Segment<'a>),
+    Index(usize),
+}
+
+/// Path error.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum PathError {
+    #[error("empty path")]
+    Empty,
+    #[error("invalid path `{0}` at offset {1}")]
+    Syntax(String, usize),
+    #[error("key `{0}` not found")]
+    NotFound(String),
+    #[error("index `{0}` out of bounds of length {1}")]
+    OutOfBounds(String, usize),
+    #[error("expected {1} at `{0}`, found {2}")]
+    Type(String, &'static str, &'static str),
+}
+
+fn is_whitespace(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+/// Parses the key segment at the start of the input.
+///
+/// Returns the segment with its length, or the offset of the error.
+pub(crate) fn segment(input: &str) -> Result<(Segment<'_>, usize), usize> {
+    match input.chars().next() {
+        Some('\'') => {
+            let end = input[1..].find('\'').ok_or(input.len())? + 1;
+            let quoted = Quoted::SingleLine(Quotes::Single(Cow::Borrowed(&input[1..end])));
+            Ok((Segment::Quoted(quoted), end + 1))
+        }
+        Some('"') => {
+            let mut is_escaped = false;
+            let end = input[1..]
+                .char_indices()
+                .find(|&(_, c)| {
+                    let is_end = c == '"' && !is_escaped;
+                    is_escaped = c == '\\' && !is_escaped;
+                    is_end
+                })
+                .ok_or(input.len())?
+                .0
+                + 1;
+            let unescaped = unescape(&input[1..end], Mode::SingleLine)
+                .collect::<Result<String, _>>()
+                .map_err(|error| error.span().start + 1)?;
+            let quoted = Quoted::SingleLine(Quotes::Double(Cow::Owned(unescaped)));
+            Ok((Segment::Quoted(quoted), end + 1))
+        }
+        _ => {
+            let len = input
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '-')
+                .unwrap_or(input.len());
+            if len == 0 {
+                return Err(0);
+            }
+            Ok((Segment::Unquoted(Cow::Borrowed(&input[..len])), len))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+
+    const INPUT: &str = r#"
+[[servers]]
+host = 'a'
+ports = [80, 443]
+
+[[servers]]
+host = 'b'
+
+["server.name".'a b']
+c = 1
+"#;
+
+    #[test]
+    fn get() {
+        let table = parse(INPUT).unwrap();
+        let host = |path| table.get_path(path).unwrap().as_string().unwrap().as_str();
+        assert_eq!(host("servers[0].host"), "a");
+        assert_eq!(host("servers[1].host"), "b");
+        assert_eq!(
+            table.get_path("servers[0].ports[1]").unwrap().value,
+            Value::from(443),
+        );
+        assert_eq!(
+            table.get_path(r#""server.name"."a b".c"#).unwrap().value,
+            Value::from(1),
+        );
+        assert_eq!(
+            table.get_path("\"server.name\" . 'a b'\t.c").unwrap().value,
+            Value::from(1),
+        );
+        assert_eq!(host("servers[1] .host"), "b");
+        let servers = &table["servers"].value;
+        assert_eq!(
+            servers.get_path("[1].host").unwrap().value,
+            Value::from("b".to_owned()),
+        );
+    }
+
+    #[test]
+    fn error() {
+        let table = parse(INPUT).unwrap();
+        let error = |path| table.get_path(path).unwrap_err();
+        assert_eq!(error(""), PathError::Empty);
+        assert_eq!(error("a..b"), PathError::Syntax("a..b".to_owned(), 2));
+        assert_eq!(error("a . "), PathError::Syntax("a . ".to_owned(), 4));
+        assert_eq!(error("a b"), PathError::Syntax("a b".to_owned(), 2));
+        assert_eq!(error("a[x]"), PathError::Syntax("a[x]".to_owned(), 2));
+        assert_eq!(error("'a"), PathError::Syntax("'a".to_owned(), 2));
+        assert_eq!(
+            error("servers[0].user"),
+            PathError::NotFound("servers[0].user".to_owned()),
+        );
+        assert_eq!(
+            error("servers[2].host"),
+            PathError::OutOfBounds("servers[2]".to_owned(), 2),
+        );
+        assert_eq!(
+            error("servers[0].host.name"),
+            PathError::Type("servers[0].host".to_owned(), "table", "string"),
+        );
+        assert_eq!(
+            error("servers.host").to_string(),
+            "expected table at `servers`, found array",
+        );
+    }
+
+    #[test]
+    fn insert() {
+        let mut table = parse(INPUT).unwrap();
+        assert_eq!(
+            table.insert_path("servers[0].host", Value::from("c".to_owned())),
+            Ok(Some(Item::from(Value::from("a".to_owned())))),
+        );
+        assert_eq!(
+            table.insert_path("servers[0].ports[2]", Value::from(8080)),
+            Ok(None)
+        );
+        assert_eq!(
+            table.insert_path("servers[2].host", Value::from("d".to_owned())),
+            Ok(None)
+        );
+        assert_eq!(table.insert_path("a.b.c", Value::from(true)), Ok(None));
+        assert_eq!(
+            table
+                .get_path("servers[0].ports")
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(
+            table.get_path("servers[2].host").unwrap().value,
+            Value::from("d".to_owned()),
+        );
+        assert!(table.get_path("a.b").unwrap().is_table());
+        assert_eq!(
+            table.insert_path("servers[4].host", Value::from(1)),
+            Err(PathError::OutOfBounds("servers[4]".to_owned(), 3)),
+        );
+        assert_eq!(
+            table.insert_path("a.b.c.d", Value::from(1)),
+            Err(PathError::Type("a.b.c".to_owned(), "table", "boolean")),
+        );
+        // Nothing is created before the failing step.
+        let unchanged = table.clone();
+        assert_eq!(
+            table.insert_path("e.f[0]", Value::from(1)),
+            Err(PathError::Type("e.f".to_owned(), "array", "table")),
+        );
+        assert_eq!(
+            table.insert_path("servers[3].g[0].h", Value::from(1)),
+            Err(PathError::Type("servers[3].g".to_owned(), "array", "table")),
+        );
+        assert_eq!(
+            table.insert_path("a.i.j.k", Value::from(1)).ok(),
+            Some(None)
+        );
+        assert_eq!(table.remove_path("a.i").map(|_| ()), Ok(()));
+        assert_eq!(table, unchanged);
+        let mut value = table["servers"].value.clone();
+        assert_eq!(
+            value.insert_path("[3].l[0]", Value::from(1)),
+            Err(PathError::Type("[3].l".to_owned(), "array", "table")),
+        );
+        assert_eq!(value, table["servers"].value);
+    }
+
+    #[test]
+    fn remove() {
+        let mut table = parse(INPUT).unwrap();
+        assert_eq!(
+            table.remove_path("servers[0].ports[0]").unwrap().value,
+            Value::from(80),
+        );
+        assert_eq!(
+            table
+                .remove_path("servers[1]")
+                .unwrap()
+                .get_path("host")
+                .unwrap()
+                .value,
+            Value::from("b".to_owned()),
+        );
+        assert_eq!(
+            table
+                .remove_path("servers")
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            table.remove_path("servers"),
+            Err(PathError::NotFound("servers".to_owned())),
+        );
+        table
+            .get_path_mut(r#""server.name"."a b".c"#)
+            .unwrap()
+            .value = Value::from(2);
+        assert_eq!(table[r#"server.name"#]["a b"]["c"].value, Value::from(2));
+    }
+}
@@ -0,0 +1,698 @@
+use crate::{
+    datetime::Datetime,
+    key::Segment,
+    path::{self, segment, Path, PathError},
+    quotes::Quoted,
+    value::{Array, Float, Integer, Item, Primitive, Table, Value},
+};
+use std::{cmp::Ordering, ops::Range};
+
+impl Table {
+    /// Returns the items matching the selector, with their paths.
+    ///
+    /// See [`Selector`] for the syntax.
+    pub fn select(&self, selector: &str) -> Result<Vec<Match<'_>>, PathError> {
+        let selector = Selector::parse(selector)?;
+        let mut matches = Vec::new();
+        selector.visit(0, Node::Table(self), &mut Vec::new(), &mut |path, item| {
+            matches.push(Match { path, item })
+        });
+        Ok(matches)
+    }
+
+    /// Calls the function on every item matching the selector.
+    ///
+    /// Descendants are visited after their ancestor is changed. Returns the
+    /// number of matches.
+    pub fn select_mut<F>(&mut self, selector: &str, mut f: F) -> Result<usize, PathError>
+    where
+        F: FnMut(&Path, &mut Item),
+    {
+        let selector = Selector::parse(selector)?;
+        let mut count = 0;
+        selector.visit_mut(
+            0,
+            NodeMut::Table(self),
+            &mut Vec::new(),
+            &mut |path, item| {
+                count += 1;
+                f(&path, item)
+            },
+        );
+        Ok(count)
+    }
+}
+
+/// Matching item.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match<'a> {
+    /// The full path of the item.
+    pub path: Path<'static>,
+    pub item: &'a Item,
+}
+
+/// Selector.
+///
+/// A path extended with:
+/// - wildcards: `servers.*`, `ports[*]`,
+/// - recursive descent: `..version`, `dependencies..*`,
+/// - array slices: `ports[-1]`, `ports[1:]`, `ports[:2]`,
+/// - predicate filters on primitive values: `bin[?name ^= 'test']`,
+///   `ports[?@ >= 1024]`, `bin[?required]`.
+///
+/// The predicate operators are `==`, `!=`, `<`, `<=`, `>`, `>=` and, for
+/// strings, `^=` (starts with), `$=` (ends with) and `*=` (contains).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selector<'a>(Vec<Step<'a>>);
+
+impl<'a> Selector<'a> {
+    /// Parses the selector.
+    pub fn parse(input: &'a str) -> Result<Self, PathError> {
+        let mut parser = Parser { input, offset: 0 };
+        let mut steps = Vec::new();
+        while !parser.is_empty() {
+            if parser.eat("..") {
+                steps.push(Step::Descendants);
+                if !parser.peek("[") {
+                    steps.push(parser.key()?);
+                }
+            } else if parser.eat("[") {
+                steps.push(parser.bracket()?);
+            } else if steps.is_empty() || parser.eat(".") {
+                steps.push(parser.key()?);
+            } else {
+                return Err(parser.error());
+            }
+        }
+        if steps.is_empty() {
+            return Err(PathError::Empty);
+        }
+        Ok(Self(steps))
+    }
+}
+
+impl Selector<'_> {
+    fn visit<'b, F>(
+        &self,
+        at: usize,
+        node: Node<'b>,
+        path: &mut Vec<path::Step<'static>>,
+        f: &mut F,
+    ) where
+        F: FnMut(Path<'static>, &'b Item),
+    {
+        let step = match self.0.get(at) {
+            Some(step) => step,
+            None => {
+                if let Node::Item(item) = node {
+                    f(Path(path.clone()), item);
+                }
+                return;
+            }
+        };
+        let (children, next) = match step {
+            Step::Descendants => {
+                self.visit(at + 1, node, path, f);
+                (node.children(), at)
+            }
+            step => (step.select(node), at + 1),
+        };
+        for (segment, child) in children {
+            path.push(segment);
+            self.visit(next, Node::Item(child), path, f);
+            path.pop();
+        }
+    }
+
+    fn visit_mut<F>(
+        &self,
+        at: usize,
+        mut node: NodeMut,
+        path: &mut Vec<path::Step<'static>>,
+        f: &mut F,
+    ) where
+        F: FnMut(Path<'static>, &mut Item),
+    {
+        let step = match self.0.get(at) {
+            Some(step) => step,
+            None => {
+                if let NodeMut::Item(item) = node {
+                    f(Path(path.clone()), item);
+                }
+                return;
+            }
+        };
+        let (children, next) = match step {
+            Step::Descendants => {
+                self.visit_mut(at + 1, node.reborrow(), path, f);
+                (node.children(), at)
+            }
+            step => (step.select_mut(node), at + 1),
+        };
+        for (segment, child) in children {
+            path.push(segment);
+            self.visit_mut(next, NodeMut::Item(child), path, f);
+            path.pop();
+        }
+    }
+}
+
+/// Selector step.
+#[derive(Clone, Debug, PartialEq)]
+enum Step<'a> {
+    Key(Segment<'a>),
+    /// Every child of a table or an array.
+    Wildcard,
+    /// The node itself and all of its descendants.
+    Descendants,
+    Index(isize),
+    Slice(Option<isize>, Option<isize>),
+    Filter(Filter<'a>),
+}
+
+impl Step<'_> {
+    /// Returns the range of the index or the slice in an array of the length,
+    /// negative bounds count from the end.
+    fn range(&self, len: usize) -> Range<usize> {
+        let bound = |bound: isize| {
+            if bound < 0 {
+                len.saturating_sub(bound.unsigned_abs())
+            } else {
+                (bound as usize).min(len)
+            }
+        };
+        match *self {
+            Self::Index(index) if index < -(len as isize) => 0..0,
+            Self::Index(index) => {
+                let start = bound(index);
+                start..(start + 1).min(len)
+            }
+            Self::Slice(start, end) => {
+                let start = start.map_or(0, bound);
+                start..end.map_or(len, bound).max(start)
+            }
+            _ => 0..0,
+        }
+    }
+
+    fn select<'b>(&self, node: Node<'b>) -> Vec<(path::Step<'static>, &'b Item)> {
+        match (self, node.table(), node.array()) {
+            (Self::Key(key), Some(table), _) => table
+                .get_key_value(&**key)
+                .map(|(key, item)| (path::Step::Key(Segment::new(key.clone())), item))
+                .into_iter()
+                .collect(),
+            (Self::Wildcard, ..) | (Self::Descendants, ..) => node.children(),
+            (Self::Index(_), _, Some(array)) | (Self::Slice(..), _, Some(array)) => {
+                let range = self.range(array.len());
+                elements(array)
+                    .skip(range.start)
+                    .take(range.len())
+                    .collect()
+            }
+            (Self::Filter(filter), ..) => node
+                .children()
+                .into_iter()
+                .filter(|(_, item)| filter.test(item))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn select_mut<'b>(&self, node: NodeMut<'b>) -> Vec<(path::Step<'static>, &'b mut Item)> {
+        match self {
+            Self::Key(key) => match node.into_table() {
+                Some(table) => table
+                    .get_full_mut(&**key)
+                    .map(|(_, key, item)| (path::Step::Key(Segment::new(key.clone())), item))
+                    .into_iter()
+                    .collect(),
+                None => Vec::new(),
+            },
+            Self::Wildcard | Self::Descendants => node.children(),
+            Self::Index(_) | Self::Slice(..) => match node.into_array() {
+                Some(array) => {
+                    let range = self.range(array.len());
+                    elements_mut(array)
+                        .skip(range.start)
+                        .take(range.len())
+                        .collect()
+                }
+                None => Vec::new(),
+            },
+            Self::Filter(filter) => node
+                .children()
+                .into_iter()
+                .filter(|(_, item)| filter.test(item))
+                .collect(),
+        }
+    }
+}
+
+/// Predicate filter.
+#[derive(Clone, Debug, PartialEq)]
+struct Filter<'a> {
+    /// The path of the tested value relative to the child, the child itself
+    /// if there is none.
+    operand: Option<Path<'a>>,
+    /// Tests the existence of the value if there is none.
+    condition: Option<(Operator, Primitive)>,
+}
+
+impl Filter<'_> {
+    fn test(&self, item: &Item) -> bool {
+        let value = match &self.operand {
+            Some(path) => match path.get(&item.value) {
+                Ok(item) => &item.value,
+                Err(_) => return false,
+            },
+            None => &item.value,
+        };
+        let (operator, literal) = match &self.condition {
+            Some(condition) => condition,
+            None => return true,
+        };
+        let primitive = match value {
+            Value::Primitive(primitive) => primitive,
+            _ => return false,
+        };
+        let ordering = compare(primitive, literal);
+        match (operator, primitive, literal) {
+            (Operator::Eq, ..) => ordering == Some(Ordering::Equal),
+            (Operator::Ne, ..) => ordering != Some(Ordering::Equal),
+            (Operator::Lt, ..) => ordering == Some(Ordering::Less),
+            (Operator::Le, ..) => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            (Operator::Gt, ..) => ordering == Some(Ordering::Greater),
+            (Operator::Ge, ..) => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            (Operator::StartsWith, Primitive::String(a), Primitive::String(b)) => {
+                a.starts_with(b.as_str())
+            }
+            (Operator::EndsWith, Primitive::String(a), Primitive::String(b)) => {
+                a.ends_with(b.as_str())
+            }
+            (Operator::Contains, Primitive::String(a), Primitive::String(b)) => {
+                a.contains(b.as_str())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Predicate operator.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    StartsWith,
+    EndsWith,
+    Contains,
+}
+
+impl Operator {
+    /// The operators, the longer ones before their prefixes.
+    const ALL: [(&'static str, Self); 9] = [
+        ("==", Self::Eq),
+        ("!=", Self::Ne),
+        ("<=", Self::Le),
+        (">=", Self::Ge),
+        ("<", Self::Lt),
+        (">", Self::Gt),
+        ("^=", Self::StartsWith),
+        ("$=", Self::EndsWith),
+        ("*=", Self::Contains),
+    ];
+}
+
+/// Node of the selector evaluation.
+#[derive(Clone, Copy)]
+enum Node<'a> {
+    Table(&'a Table),
+    Item(&'a Item),
+}
+
+impl<'a> Node<'a> {
+    fn table(self) -> Option<&'a Table> {
+        match self {
+            Self::Table(table) => Some(table),
+            Self::Item(item) => item.as_table(),
+        }
+    }
+
+    fn array(self) -> Option<&'a Array> {
+        match self {
+            Self::Table(_) => None,
+            Self::Item(item) => item.as_array(),
+        }
+    }
+
+    fn children(self) -> Vec<(path::Step<'static>, &'a Item)> {
+        match (self.table(), self.array()) {
+            (Some(table), _) => table
+                .iter()
+                .map(|(key, item)| (path::Step::Key(Segment::new(key.clone())), item))
+                .collect(),
+            (_, Some(array)) => elements(array).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Mutable node of the selector evaluation.
+enum NodeMut<'a> {
+    Table(&'a mut Table),
+    Item(&'a mut Item),
+}
+
+impl<'a> NodeMut<'a> {
+    fn reborrow(&mut self) -> NodeMut<'_> {
+        match self {
+            Self::Table(table) => NodeMut::Table(table),
+            Self::Item(item) => NodeMut::Item(item),
+        }
+    }
+
+    fn into_table(self) -> Option<&'a mut Table> {
+        match self {
+            Self::Table(table) => Some(table),
+            Self::Item(item) => item.as_table_mut(),
+        }
+    }
+
+    fn into_array(self) -> Option<&'a mut Array> {
+        match self {
+            Self::Table(_) => None,
+            Self::Item(item) => item.as_array_mut(),
+        }
+    }
+
+    fn children(self) -> Vec<(path::Step<'static>, &'a mut Item)> {
+        let value = match self {
+            Self::Table(table) => return entries_mut(table),
+            Self::Item(item) => &mut item.value,
+        };
+        match value {
+            Value::Table(table) => entries_mut(table),
+            Value::Array(array) => elements_mut(array).collect(),
+            Value::Primitive(_) => Vec::new(),
+        }
+    }
+}
+
+fn entries_mut(table: &mut Table) -> Vec<(path::Step<'static>, &mut Item)> {
+    table
+        .iter_mut()
+        .map(|(key, item)| (path::Step::Key(Segment::new(key.clone())), item))
+        .collect()
+}
+
+fn elements(array: &Array) -> impl Iterator<Item = (path::Step<'static>, &Item)> {
+    array
+        .iter()
+        .enumerate()
+        .map(|(index, item)| (path::Step::Index(index), item))
+}
+
+fn elements_mut(array: &mut Array) -> impl Iterator<Item = (path::Step<'static>, &mut Item)> {
+    array
+        .iter_mut()
+        .enumerate()
+        .map(|(index, item)| (path::Step::Index(index), item))
+}
+
+/// Compares the primitives, strings by their content and integers with
+/// floats by their values.
+fn compare(a: &Primitive, b: &Primitive) -> Option<Ordering> {
+    match (a, b) {
+        (Primitive::String(a), Primitive::String(b)) => a.as_str().partial_cmp(b.as_str()),
+        (Primitive::Integer(a), Primitive::Float(b, _)) => {
+            (i64::from(*a) as f64).partial_cmp(&f64::from(b))
+        }
+        (Primitive::Float(a, _), Primitive::Integer(b)) => {
+            f64::from(a).partial_cmp(&(i64::from(*b) as f64))
+        }
+        _ => a.partial_cmp(b),
+    }
+}
+
+/// Selector parser.
+struct Parser<'a> {
+    input: &'a str,
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset == self.input.len()
+    }
+
+    fn error(&self) -> PathError {
+        PathError::Syntax(self.input.to_owned(), self.offset)
+    }
+
+    fn peek(&self, token: &str) -> bool {
+        self.rest().starts_with(token)
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let is_token = self.peek(token);
+        if is_token {
+            self.offset += token.len();
+        }
+        is_token
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    }
+
+    fn segment(&mut self) -> Result<Segment<'a>, PathError> {
+        let (segment, len) = segment(self.rest())
+            .map_err(|index| PathError::Syntax(self.input.to_owned(), self.offset + index))?;
+        self.offset += len;
+        Ok(segment)
+    }
+
+    /// Parses a key or a wildcard.
+    fn key(&mut self) -> Result<Step<'a>, PathError> {
+        if self.eat("*") {
+            return Ok(Step::Wildcard);
+        }
+        Ok(Step::Key(self.segment()?))
+    }
+
+    fn integer(&mut self) -> Result<Option<isize>, PathError> {
+        let rest = self.rest();
+        let len = rest
+            .char_indices()
+            .find(|&(index, c)| !(c.is_ascii_digit() || index == 0 && c == '-'))
+            .map_or(rest.len(), |(index, _)| index);
+        if len == 0 {
+            return Ok(None);
+        }
+        let integer = rest[..len].parse().map_err(|_| self.error())?;
+        self.offset += len;
+        Ok(Some(integer))
+    }
+
+    /// Parses the inside of brackets, and the closing bracket.
+    fn bracket(&mut self) -> Result<Step<'a>, PathError> {
+        let step = if self.eat("*") {
+            Step::Wildcard
+        } else if self.eat("?") {
+            Step::Filter(self.filter()?)
+        } else {
+            let start = self.integer()?;
+            if self.eat(":") {
+                Step::Slice(start, self.integer()?)
+            } else {
+                Step::Index(start.ok_or_else(|| self.error())?)
+            }
+        };
+        if !self.eat("]") {
+            return Err(self.error());
+        }
+        Ok(step)
+    }
+
+    fn filter(&mut self) -> Result<Filter<'a>, PathError> {
+        self.skip_whitespace();
+        let mut steps = Vec::new();
+        if !self.eat("@") {
+            steps.push(path::Step::Key(self.segment()?));
+        }
+        loop {
+            if self.eat(".") {
+                steps.push(path::Step::Key(self.segment()?));
+            } else if self.eat("[") {
+                match self.integer()? {
+                    Some(index) if index >= 0 && self.eat("]") => {
+                        steps.push(path::Step::Index(index as usize))
+                    }
+                    _ => return Err(self.error()),
+                }
+            } else {
+                break;
+            }
+        }
+        let operand = if steps.is_empty() {
+            None
+        } else {
+            Some(Path(steps))
+        };
+        self.skip_whitespace();
+        let operator = Operator::ALL
+            .iter()
+            .find(|(token, _)| self.peek(token))
+            .map(|&(token, operator)| (token, operator));
+        let condition = match operator {
+            Some((token, operator)) => {
+                self.offset += token.len();
+                self.skip_whitespace();
+                Some((operator, self.literal()?))
+            }
+            None => None,
+        };
+        self.skip_whitespace();
+        Ok(Filter { operand, condition })
+    }
+
+    fn literal(&mut self) -> Result<Primitive, PathError> {
+        if self.peek("'") || self.peek("\"") {
+            let segment = self.segment()?;
+            return Ok(Primitive::String(Quoted::new(String::from(segment))));
+        }
+        let rest = self.rest();
+        let len = rest.find([']', ' ', '\t']).unwrap_or(rest.len());
+        let literal = &rest[..len];
+        let primitive = match literal {
+            "true" => Primitive::Boolean(true),
+            "false" => Primitive::Boolean(false),
+            _ => {
+                if let Ok(integer) = literal.parse::<Integer>() {
+                    Primitive::Integer(integer)
+                } else if let Ok((float, literal)) = Float::parse_literal(literal) {
+                    Primitive::Float(float, literal)
+                } else if let Ok(date_time) = literal.parse::<Datetime>() {
+                    Primitive::DateTime(date_time)
+                } else {
+                    return Err(self.error());
+                }
+            }
+        };
+        self.offset += len;
+        Ok(primitive)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+
+    const INPUT: &str = r#"
+version = '1.0.0'
+
+[dependencies]
+a = { version = '0.1' }
+b = '0.2'
+
+[[bin]]
+name = 'test-a'
+ports = [80, 443, 8080]
+
+[[bin]]
+name = 'main'
+required = true
+
+[[bin]]
+name = 'test-b'
+"#;
+
+    fn paths(table: &Table, selector: &str) -> Vec<String> {
+        table
+            .select(selector)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.path.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn select() {
+        let table = parse(INPUT).unwrap();
+        assert_eq!(
+            paths(&table, "dependencies.*"),
+            ["dependencies.a", "dependencies.b"]
+        );
+        assert_eq!(
+            paths(&table, "..version"),
+            ["version", "dependencies.a.version"]
+        );
+        assert_eq!(paths(&table, "bin[*].name").len(), 3);
+        assert_eq!(paths(&table, "bin[-1].name"), ["bin[2].name"]);
+        assert_eq!(
+            paths(&table, "bin[1:].name"),
+            ["bin[1].name", "bin[2].name"]
+        );
+        assert_eq!(paths(&table, "bin[:1]"), ["bin[0]"]);
+        assert_eq!(paths(&table, "bin[?name ^= 'test']"), ["bin[0]", "bin[2]"]);
+        assert_eq!(paths(&table, "bin[?name $= \"b\"].name"), ["bin[2].name"]);
+        assert_eq!(paths(&table, "bin[?required]"), ["bin[1]"]);
+        assert_eq!(paths(&table, "bin[?required == true]"), ["bin[1]"]);
+        assert_eq!(
+            paths(&table, "bin[0].ports[?@ >= 443]"),
+            ["bin[0].ports[1]", "bin[0].ports[2]"]
+        );
+        assert_eq!(paths(&table, "bin..ports[?@ < 100.5]"), ["bin[0].ports[0]"]);
+        assert_eq!(paths(&table, "missing.*"), Vec::<String>::new());
+        let matches = table.select("bin[?name == 'main'].required").unwrap();
+        assert_eq!(matches[0].item.value, Value::from(true));
+    }
+
+    #[test]
+    fn error() {
+        let table = parse(INPUT).unwrap();
+        assert_eq!(table.select(""), Err(PathError::Empty));
+        assert_eq!(
+            table.select("bin[?name ~ 'a']"),
+            Err(PathError::Syntax("bin[?name ~ 'a']".to_owned(), 10)),
+        );
+        assert_eq!(
+            table.select("bin[1"),
+            Err(PathError::Syntax("bin[1".to_owned(), 5)),
+        );
+    }
+
+    #[test]
+    fn select_mut() {
+        let mut table = parse(INPUT).unwrap();
+        let count = table
+            .select_mut("..version", |_, item| {
+                item.value = Value::from("2.0".to_owned())
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(
+            table.get_path("dependencies.a.version").unwrap().value,
+            Value::from("2.0".to_owned()),
+        );
+        let mut paths = Vec::new();
+        table
+            .select_mut("bin[?name ^= 'test'].name", |path, item| {
+                paths.push(path.to_string());
+                item.value = Value::from("renamed".to_owned());
+            })
+            .unwrap();
+        assert_eq!(paths, ["bin[0].name", "bin[2].name"]);
+        assert_eq!(table.select("bin[?name == 'renamed']").unwrap().len(), 2);
+    }
+}
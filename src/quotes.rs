@@ -38,6 +38,26 @@ impl<T> Quoted<T> {
             Self::MultiLine(quotes) => Quoted::MultiLine(quotes.map(f)),
         }
     }
+
+    /// Tests whether the quotes can hold the string.
+    ///
+    /// Literal strings can't hold control characters, nor single-line ones
+    /// line feeds and apostrophes.
+    pub(crate) fn can_hold(&self) -> bool
+    where
+        T: AsRef<str>,
+    {
+        let str = (**self).as_ref();
+        let flags = Flags::parse(str);
+        match self {
+            Self::SingleLine(Quotes::Single(_)) => {
+                !flags.has_lf_or_cr && !flags.has_escape && !flags.has_apostrophe
+            }
+            Self::MultiLine(Quotes::Single(_)) => !flags.has_escape && !str.contains("'''"),
+            Self::SingleLine(Quotes::Double(_)) => true,
+            Self::MultiLine(Quotes::Double(_)) => !str.contains(r#"""""#),
+        }
+    }
 }
 
 impl<T> Deref for Quoted<T> {
@@ -0,0 +1,901 @@
+//! Serialization into tables and values.
+//!
+//! TOML has no null: `None` and unit fields of structs and maps are omitted,
+//! and are an error anywhere else.
+
+use crate::{
+    datetime::{Datetime, SERDE_NAME},
+    error::{Error, ErrorKind, Result},
+    escape::{Flags, Span},
+    format::Independent,
+    quotes::{Quoted, Quotes},
+    value::{Array, Digits, Integer, Item, Literal, Primitive, Table, Value},
+};
+use serde::ser::{self, Impossible, Serialize, SerializeMap, SerializeSeq, SerializeStruct};
+use std::{collections::HashMap, convert::TryFrom, fmt::Display, iter::FromIterator, mem::take};
+
+/// Serializes the value into a value.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value> {
+    value.serialize(Serializer)?.ok_or_else(unsupported_none)
+}
+
+/// Serializes the value into a table.
+pub fn to_table<T: Serialize + ?Sized>(value: &T) -> Result<Table> {
+    match to_value(value)? {
+        Value::Table(table) => Ok(table),
+        value => Err(ser::Error::custom(format_args!(
+            "expected a table, found {}",
+            value.type_str()
+        ))),
+    }
+}
+
+/// Serializes the value into a TOML string.
+///
+/// Tables and arrays of tables are written with headers, the tables inside
+/// static arrays inline.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    let table = to_table(value)?;
+    Ok(Independent::new(table, |_: &[&str]| false).to_string())
+}
+
+/// Serializes the value onto the table, changing only what differs.
+///
+/// Untouched items keep their comments and spelling, changed values keep the
+/// quotes and the radix of the previous ones where they can. Keys the value
+/// lacks are removed, new keys are inserted after the key preceding them in
+/// the value.
+pub fn update<T: Serialize + ?Sized>(table: &mut Table, value: &T) -> Result<()> {
+    update_table(table, to_table(value)?);
+    Ok(())
+}
+
+fn update_table(target: &mut Table, source: Table) {
+    let mut values = HashMap::new();
+    // The new items by the key they follow, `None` for the start.
+    let mut items = HashMap::<_, Vec<_>>::new();
+    let mut previous = None;
+    for (key, item) in source {
+        if target.contains_key(&key) {
+            values.insert(key.clone(), item.value);
+            previous = Some(key);
+        } else {
+            items.entry(previous.clone()).or_default().push((key, item));
+        }
+    }
+    let mut table = Table::new();
+    table.extend(items.remove(&None).into_iter().flatten());
+    for (key, mut item) in take(target) {
+        if let Some(value) = values.remove(&key) {
+            update_value(&mut item.value, value);
+            let following = items.remove(&Some(key.clone()));
+            table.insert(key, item);
+            table.extend(following.into_iter().flatten());
+        }
+    }
+    *target = table;
+}
+
+fn update_array(target: &mut Array, source: Array) {
+    let len = source.len();
+    let mut source = source.into_iter();
+    for (target, source) in target.iter_mut().zip(&mut source) {
+        update_value(&mut target.value, source.value);
+    }
+    target.truncate(len);
+    target.extend(source);
+}
+
+fn update_value(target: &mut Value, source: Value) {
+    match (target, source) {
+        (Value::Table(target), Value::Table(source)) => update_table(target, source),
+        (Value::Array(target), Value::Array(source)) => update_array(target, source),
+        (
+            Value::Primitive(Primitive::String(target)),
+            Value::Primitive(Primitive::String(source)),
+        ) => {
+            if target.as_str() != source.as_str() {
+                *target = restyle(target, source.as_str().to_owned());
+            }
+        }
+        (
+            Value::Primitive(Primitive::Integer(target)),
+            Value::Primitive(Primitive::Integer(source)),
+        ) => {
+            if *target != source {
+                let value = i64::from(source);
+                // The digits change, so that the case is kept as a whole.
+                let uppercase = if target.digits().uppercase != 0 {
+                    u64::MAX
+                } else {
+                    0
+                };
+                let digits = Digits {
+                    uppercase,
+                    ..Digits::default()
+                };
+                *target = match target {
+                    Integer::Binary(..) if value >= 0 => Integer::Binary(value, digits),
+                    Integer::Octal(..) if value >= 0 => Integer::Octal(value, digits),
+                    Integer::Hex(..) if value >= 0 => Integer::Hex(value, digits),
+                    _ => Integer::from(value),
+                };
+            }
+        }
+        (
+            Value::Primitive(Primitive::Float(target, literal)),
+            Value::Primitive(Primitive::Float(source, _)),
+        ) => {
+            // NaN isn't equal to itself and zero is equal to negative zero.
+            let (previous, value) = (f64::from(&*target), f64::from(&source));
+            if !(previous.is_nan() && value.is_nan()) && previous.to_bits() != value.to_bits() {
+                *target = source;
+                *literal = Literal::default();
+            }
+        }
+        (target, source) => {
+            if *target != source {
+                *target = source;
+            }
+        }
+    }
+}
+
+/// Quotes the string like the previous one, if it can be quoted so.
+fn restyle(previous: &Quoted<String>, string: String) -> Quoted<String> {
+    let flags = Flags::parse(&string);
+    match previous {
+        Quoted::SingleLine(Quotes::Single(_))
+            if !flags.has_escape && !flags.has_apostrophe && !flags.has_lf_or_cr =>
+        {
+            Quoted::SingleLine(Quotes::Single(string))
+        }
+        Quoted::SingleLine(Quotes::Double(_)) => Quoted::SingleLine(Quotes::Double(string)),
+        Quoted::MultiLine(Quotes::Single(_)) if !flags.has_escape && !string.contains("'''") => {
+            Quoted::MultiLine(Quotes::Single(string))
+        }
+        Quoted::MultiLine(Quotes::Double(_)) => Quoted::MultiLine(Quotes::Double(string)),
+        _ => Quoted::new(string),
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::new(ErrorKind::Serialize(msg.to_string()), Span::new())
+    }
+}
+
+fn unsupported_none() -> Error {
+    ser::Error::custom("unsupported none value")
+}
+
+/// Wraps the value of the variant into a table with the single variant key.
+fn wrap_variant(variant: &'static str, value: Option<Value>) -> Result<Option<Value>> {
+    let value = value.ok_or_else(unsupported_none)?;
+    Ok(Some(Value::from_iter(vec![(variant, value)])))
+}
+
+/// Serializer.
+///
+/// Serializes into a value, or `None` for `None` and unit.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = VariantSerializer<SeqSerializer>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = VariantSerializer<MapSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
+        let v =
+            i64::try_from(v).map_err(|_| Error::new(ErrorKind::IntegerOverflow, Span::new()))?;
+        self.serialize_i64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok> {
+        self.serialize_str(&v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok> {
+        Ok(Some(Value::from(v.to_owned())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
+        v.serialize(self)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        let value = value.serialize(self)?;
+        if name != SERDE_NAME {
+            return Ok(value);
+        }
+        match value {
+            Some(Value::Primitive(Primitive::String(string))) => {
+                let date_time = string
+                    .parse::<Datetime>()
+                    .map_err(|_| Error::new(ErrorKind::DateTime, Span::new()))?;
+                Ok(Some(Value::from(date_time)))
+            }
+            _ => Err(Error::new(ErrorKind::DateTime, Span::new())),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        wrap_variant(variant, value.serialize(self)?)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(SeqSerializer(Array::from(Vec::<Item>::with_capacity(
+            len.unwrap_or_default(),
+        ))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Ok(VariantSerializer {
+            variant,
+            serializer: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(MapSerializer::default())
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Ok(VariantSerializer {
+            variant,
+            serializer: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+/// Sequence serializer.
+#[derive(Debug)]
+pub struct SeqSerializer(Array);
+
+impl SerializeSeq for SeqSerializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.0.push(Item::from(to_value(value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        Ok(Some(Value::Array(self.0)))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        SerializeSeq::end(self)
+    }
+}
+
+/// Map serializer.
+#[derive(Debug, Default)]
+pub struct MapSerializer {
+    table: Table,
+    key: Option<String>,
+}
+
+impl SerializeMap for MapSerializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self.key.take().expect("key before value");
+        if let Some(value) = value.serialize(Serializer)? {
+            self.table.insert(key, Item::from(value));
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        Ok(Some(Value::Table(self.table)))
+    }
+}
+
+impl SerializeStruct for MapSerializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        SerializeMap::serialize_entry(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        SerializeMap::end(self)
+    }
+}
+
+/// Variant serializer.
+#[derive(Debug)]
+pub struct VariantSerializer<T> {
+    variant: &'static str,
+    serializer: T,
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<SeqSerializer> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        SerializeSeq::serialize_element(&mut self.serializer, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        wrap_variant(self.variant, SerializeSeq::end(self.serializer)?)
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<MapSerializer> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        SerializeStruct::serialize_field(&mut self.serializer, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        wrap_variant(self.variant, SerializeMap::end(self.serializer)?)
+    }
+}
+
+/// Key serializer.
+///
+/// Keys are strings, other primitives are converted to their string form.
+struct KeySerializer;
+
+impl KeySerializer {
+    fn unsupported<T>(&self) -> Result<T> {
+        Err(ser::Error::custom("unsupported key type"))
+    }
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String> {
+        self.unsupported()
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String> {
+        self.unsupported()
+    }
+
+    fn serialize_char(self, v: char) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String> {
+        Ok(v.to_owned())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String> {
+        self.unsupported()
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        self.unsupported()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        self.unsupported()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        self.unsupported()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_owned())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String> {
+        self.unsupported()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.unsupported()
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        self.unsupported()
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.unsupported()
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.unsupported()
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.unsupported()
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        self.unsupported()
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.unsupported()
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Primitive(Primitive::String(string)) => {
+                serializer.serialize_str(string.as_str())
+            }
+            Value::Primitive(Primitive::Integer(integer)) => {
+                serializer.serialize_i64((*integer).into())
+            }
+            Value::Primitive(Primitive::Float(float, _)) => serializer.serialize_f64(float.into()),
+            Value::Primitive(Primitive::Boolean(boolean)) => serializer.serialize_bool(*boolean),
+            Value::Primitive(Primitive::DateTime(date_time)) => date_time.serialize(serializer),
+            Value::Array(array) => array.serialize(serializer),
+            Value::Table(table) => table.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for Array {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for item in self.iter() {
+            seq.serialize_element(&item.value)?;
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for Table {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, item) in self.iter() {
+            map.serialize_entry(key, &item.value)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Config {
+        title: String,
+        owner: Owner,
+        version: u32,
+        ratio: f64,
+        released: Datetime,
+        mode: Mode,
+        level: Option<u8>,
+        tags: Vec<String>,
+        points: Vec<Point>,
+        servers: Vec<Server>,
+        limits: BTreeMap<String, u16>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Owner {
+        name: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    enum Mode {
+        Debug,
+        Release { optimized: bool },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Point(i64, i64);
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Server {
+        ip: String,
+        ports: Vec<u16>,
+    }
+
+    fn config() -> Config {
+        Config {
+            title: "TOML".to_owned(),
+            owner: Owner {
+                name: "Tom".to_owned(),
+            },
+            version: 1,
+            ratio: 0.5,
+            released: "1979-05-27T07:32:00Z".parse().unwrap(),
+            mode: Mode::Release { optimized: true },
+            level: None,
+            tags: vec!["a".to_owned(), "b".to_owned()],
+            points: vec![Point(1, 2)],
+            servers: vec![
+                Server {
+                    ip: "10.0.0.1".to_owned(),
+                    ports: vec![8000, 8001],
+                },
+                Server {
+                    ip: "10.0.0.2".to_owned(),
+                    ports: vec![],
+                },
+            ],
+            limits: vec![("cpu".to_owned(), 2)].into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn serialize() {
+        let config = config();
+        let table = to_table(&config).unwrap();
+        assert!(table["released"].is_date_time());
+        assert!(table.get("level").is_none());
+        assert_eq!(
+            table["mode"]["Release"]["optimized"].value,
+            Value::from(true)
+        );
+        assert_eq!(Config::deserialize(table).unwrap(), config);
+    }
+
+    #[test]
+    fn string() {
+        let string = to_string(&config()).unwrap();
+        assert_eq!(
+            string,
+            r#"title = 'TOML'
+version = 1
+ratio = 0.5
+released = 1979-05-27T07:32:00Z
+tags = ['a', 'b']
+points = [[1, 2]]
+
+[owner]
+name = 'Tom'
+
+[mode.Release]
+optimized = true
+
+[[servers]]
+ip = '10.0.0.1'
+ports = [8000, 8001]
+
+[[servers]]
+ip = '10.0.0.2'
+ports = []
+
+[limits]
+cpu = 2
+"#,
+        );
+        assert_eq!(crate::from_str::<Config>(&string).unwrap(), config());
+    }
+
+    #[test]
+    fn update() {
+        #[derive(Deserialize, Serialize)]
+        struct Settings {
+            name: String,
+            mask: i64,
+            port: u16,
+            theme: Option<String>,
+            servers: Vec<Owner>,
+        }
+
+        let input = r#"# The name.
+name = "default" # Changed.
+# Removed.
+theme = 'dark'
+# The mask.
+mask = 0xFF
+port = 8_080
+
+# Servers.
+[[servers]]
+name = 'a' # First.
+
+[[servers]]
+name = 'b'
+"#;
+        let mut table = crate::parse(input).unwrap();
+        let mut settings = Settings::deserialize(table.clone()).unwrap();
+        settings.name = "it's".to_owned();
+        settings.mask = 0xAB;
+        settings.theme = None;
+        settings.servers.pop();
+        super::update(&mut table, &settings).unwrap();
+        let is_inline = |_: &[&str]| false;
+        assert_eq!(
+            Independent::new(&table, is_inline).to_string(),
+            r#"# The name.
+name = "it's" # Changed.
+# The mask.
+mask = 0xAB
+port = 8_080
+
+# Servers.
+[[servers]]
+name = 'a' # First.
+"#,
+        );
+    }
+
+    #[test]
+    fn update_float_datetime() {
+        #[derive(Deserialize, Serialize)]
+        struct Values {
+            nan: f64,
+            zero: f64,
+            released: Datetime,
+        }
+
+        let input = "nan = +nan\nzero = 0.0\nreleased = 1979-05-27T07:32:00-07:00\n";
+        let mut table = crate::parse(input).unwrap();
+        let mut values = Values::deserialize(table.clone()).unwrap();
+        super::update(&mut table, &values).unwrap();
+        let is_inline = |_: &[&str]| false;
+        assert_eq!(Independent::new(&table, is_inline).to_string(), input);
+        values.zero = -0.0;
+        values.released = "1979-05-27T14:32:00Z".parse().unwrap();
+        super::update(&mut table, &values).unwrap();
+        assert_eq!(
+            Independent::new(&table, is_inline).to_string(),
+            "nan = +nan\nzero = -0.0\nreleased = 1979-05-27T14:32:00Z\n",
+        );
+    }
+
+    #[test]
+    fn update_new_keys() {
+        let mut table = crate::parse("b = 1\nd = 1").unwrap();
+        let value: BTreeMap<_, _> = vec![("a", 0), ("b", 1), ("c", 2), ("d", 1), ("e", 3)]
+            .into_iter()
+            .collect();
+        super::update(&mut table, &value).unwrap();
+        assert_eq!(table.keys().collect::<Vec<_>>(), ["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn error() {
+        assert!(to_value(&None::<u8>).is_err());
+        assert!(to_value(&vec![Some(1), None]).is_err());
+        assert!(to_table(&1).is_err());
+        assert_eq!(
+            to_value(&u64::MAX).unwrap_err().kind,
+            ErrorKind::IntegerOverflow,
+        );
+        let table = to_value(&vec![(1, "a")].into_iter().collect::<BTreeMap<_, _>>()).unwrap();
+        assert_eq!(table["1"].value, Value::from("a".to_owned()));
+    }
+}
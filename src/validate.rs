@@ -0,0 +1,252 @@
+use crate::{
+    ast::Kind,
+    error::{Error, ErrorKind, Location, Result},
+    escape::Span,
+    key::Key,
+};
+use indexmap::map::{Entry, IndexMap};
+use std::iter::FromIterator;
+
+/// Validator.
+///
+/// Tracks how every key is defined, to reject the definitions TOML forbids:
+/// duplicate keys, tables defined twice, and values or tables extended the
+/// wrong way.
+#[derive(Debug, Default)]
+pub(crate) struct Validator {
+    root: Node,
+    /// The key of the current table.
+    table: Vec<String>,
+}
+
+impl Validator {
+    /// Validates the header and makes its table the current one.
+    pub(crate) fn header(&mut self, header: Kind<&Key>, span: Span) -> Result<()> {
+        let (key, definition) = match header {
+            Kind::ArrayOfTables(key) => (key, Definition::ArrayOfTables),
+            Kind::Table(key) => (key, Definition::Header),
+        };
+        let mut path = Vec::with_capacity(key.len());
+        let mut node = &mut self.root;
+        for (index, segment) in key.iter().enumerate() {
+            path.push(String::from(&**segment));
+            let is_last = index + 1 == key.len();
+            node = match node.children.entry(path[index].clone()) {
+                Entry::Vacant(entry) if is_last => entry.insert(Node::new(definition, span)),
+                Entry::Vacant(entry) => entry.insert(Node::new(Definition::Implicit, span)),
+                Entry::Occupied(entry) => {
+                    let child = entry.into_mut();
+                    match (child.definition, definition) {
+                        (Definition::Value, Definition::Header) if is_last => {
+                            return Err(child.error(ErrorKind::DuplicateKey, &path, span));
+                        }
+                        (Definition::Value, _) => {
+                            return Err(child.error(ErrorKind::ExtendValue, &path, span));
+                        }
+                        _ if !is_last => {}
+                        (Definition::Implicit, Definition::Header) => {
+                            child.definition = Definition::Header;
+                            child.span = span;
+                        }
+                        (Definition::ArrayOfTables, Definition::ArrayOfTables) => {
+                            child.children.clear();
+                            child.span = span;
+                        }
+                        _ => return Err(child.error(ErrorKind::DuplicateTable, &path, span)),
+                    }
+                    child
+                }
+            };
+        }
+        self.table = path;
+        Ok(())
+    }
+
+    /// Validates the key of the key-value in the current table.
+    pub(crate) fn key_value(&mut self, key: &Key, span: Span) -> Result<()> {
+        let mut path = self.table.clone();
+        let mut node = &mut self.root;
+        for segment in &self.table {
+            node = node.children.get_mut(segment).expect("current table");
+        }
+        for (index, segment) in key.iter().enumerate() {
+            path.push(String::from(&**segment));
+            let is_last = index + 1 == key.len();
+            node = match node.children.entry(path[path.len() - 1].clone()) {
+                Entry::Vacant(entry) if is_last => entry.insert(Node::new(Definition::Value, span)),
+                Entry::Vacant(entry) => entry.insert(Node::new(Definition::Dotted, span)),
+                Entry::Occupied(entry) => {
+                    let child = entry.into_mut();
+                    match child.definition {
+                        _ if is_last => {
+                            return Err(child.error(ErrorKind::DuplicateKey, &path, span));
+                        }
+                        Definition::Dotted => {}
+                        Definition::Implicit => {
+                            child.definition = Definition::Dotted;
+                            child.span = span;
+                        }
+                        Definition::Value => {
+                            return Err(child.error(ErrorKind::ExtendValue, &path, span));
+                        }
+                        Definition::Header | Definition::ArrayOfTables => {
+                            return Err(child.error(ErrorKind::ExtendTable, &path, span));
+                        }
+                    }
+                    child
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+/// How a key is defined.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Definition {
+    /// A table created by a header of its sub-table.
+    Implicit,
+    /// A table defined by its `[table]` header.
+    Header,
+    /// An array of tables, its children are of the last table.
+    ArrayOfTables,
+    /// A table created by a dotted key.
+    Dotted,
+    /// A value, including inline tables and static arrays.
+    Value,
+}
+
+/// Defined key.
+#[derive(Debug)]
+struct Node {
+    definition: Definition,
+    /// The span of the definition.
+    span: Span,
+    children: IndexMap<String, Node>,
+}
+
+impl Node {
+    fn new(definition: Definition, span: Span) -> Self {
+        Self {
+            definition,
+            span,
+            children: IndexMap::new(),
+        }
+    }
+
+    /// Returns the error of the key conflicting with this definition.
+    fn error<F>(&self, kind: F, path: &[String], span: Span) -> Error
+    where
+        F: FnOnce(String, Location) -> ErrorKind,
+    {
+        let key = Key::from_iter(path.iter().cloned());
+        Error::new(kind(key.to_string(), Location::new(self.span)), span)
+    }
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Self::new(Definition::Implicit, Span::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{parse, ErrorKind};
+
+    fn error(input: &str) -> ErrorKind {
+        parse(input).unwrap_err().kind
+    }
+
+    #[test]
+    fn valid() {
+        for input in &[
+            "a.b = 1\na.c = 2",
+            "[a.b]\n[a]\n[a.c]",
+            "[a]\nb.c = 1\n[a.b.d]",
+            "[[a]]\nb = 1\n[[a]]\nb = 2",
+            "[[a]]\n[a.b]\nc = 1\n[[a]]\n[a.b]\nc = 2",
+            "[[a]]\n[[a.b]]\n[[a.b]]",
+            "[a.b.c]\n[a]\nb.d = 1",
+            "a = { b = 1, c.d = 2 }",
+            "a = [{ b = 1 }, { b = 2 }]",
+        ] {
+            assert!(parse(input).is_ok(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn duplicate_key() {
+        let input = "a = 1\n\"a\" = 2";
+        let duplicate = parse(input).unwrap_err();
+        assert_eq!((duplicate.line, duplicate.column), (2, 1));
+        match &duplicate.kind {
+            ErrorKind::DuplicateKey(key, previous) => {
+                assert_eq!(key, "a");
+                assert_eq!((previous.line, previous.column), (1, 1));
+            }
+            kind => panic!("{:?}", kind),
+        }
+        assert_eq!(
+            duplicate.to_string(),
+            "duplicate key `a` (first defined at 1:1) at line 2, column 1",
+        );
+        assert!(matches!(
+            error("[a]\nb = 1\n[a.b]"),
+            ErrorKind::DuplicateKey(..)
+        ));
+        assert!(matches!(
+            error("a = { b = 1, b = 2 }"),
+            ErrorKind::DuplicateKey(..)
+        ));
+        assert!(matches!(
+            error("a.b = 1\na = 2"),
+            ErrorKind::DuplicateKey(..)
+        ));
+    }
+
+    #[test]
+    fn duplicate_table() {
+        assert!(matches!(error("[a]\n[a]"), ErrorKind::DuplicateTable(..)));
+        assert!(matches!(error("[a]\n[[a]]"), ErrorKind::DuplicateTable(..)));
+        assert!(matches!(error("[[a]]\n[a]"), ErrorKind::DuplicateTable(..)));
+        assert!(matches!(
+            error("a.b = 1\n[a]"),
+            ErrorKind::DuplicateTable(..)
+        ));
+        assert!(matches!(
+            error("[a]\nb.c = 1\n[a.b]"),
+            ErrorKind::DuplicateTable(..)
+        ));
+    }
+
+    #[test]
+    fn extend_value() {
+        assert!(matches!(
+            error("a = 1\na.b = 2"),
+            ErrorKind::ExtendValue(..)
+        ));
+        assert!(matches!(error("a = {}\n[a.b]"), ErrorKind::ExtendValue(..)));
+        assert!(matches!(error("a = []\n[[a]]"), ErrorKind::ExtendValue(..)));
+        assert!(matches!(
+            error("a = { b.c = 1, b = 2 }"),
+            ErrorKind::DuplicateKey(..)
+        ));
+        assert!(matches!(
+            error("a = { b = 1, b.c = 2 }"),
+            ErrorKind::ExtendValue(..)
+        ));
+    }
+
+    #[test]
+    fn extend_table() {
+        assert!(matches!(
+            error("[a.b]\n[a]\nb.c = 1"),
+            ErrorKind::ExtendTable(..)
+        ));
+        assert!(matches!(
+            error("[a.b.c]\n[a]\nb.c.d = 1"),
+            ErrorKind::ExtendTable(..)
+        ));
+    }
+}
@@ -1,26 +1,46 @@
-use crate::{comment::Comments, key::Key, merge::Merge, quotes::Quoted};
+use crate::{
+    comment::Comments,
+    datetime::Datetime,
+    error::ErrorKind,
+    key::{Key, Style},
+    merge::{check_table, merge_table, MergeOptions},
+    quotes::Quoted,
+};
 use chrono::{DateTime, FixedOffset};
-use derive_more::{Deref, DerefMut, Display, From, Into, IntoIterator};
+use derive_more::{Deref, DerefMut, From, Into, IntoIterator};
 use derive_new::new;
 use indexmap::{indexmap, IndexMap};
 use optional_index::{OptionalIndex, OptionalIndexMut};
 use std::{
     borrow::Borrow,
     cmp::Ordering,
-    fmt::Debug,
+    fmt::{self, Debug, Display, Formatter, Write},
     hash::Hash,
     iter::FromIterator,
     mem::discriminant,
     ops::{Index, IndexMut},
+    str::FromStr,
 };
 
 /// Item.
-#[derive(Clone, Debug, Deref, DerefMut, PartialEq, new)]
+#[derive(Clone, Debug, Deref, DerefMut, new)]
 pub struct Item {
     pub comments: Comments,
     #[deref]
     #[deref_mut]
     pub value: Value,
+    /// The style of the key of the item in the input, none if the item is
+    /// constructed.
+    #[new(default)]
+    pub key_style: Option<Style>,
+}
+
+/// Items are equal when their comments and values are, whatever the style of
+/// their keys.
+impl PartialEq for Item {
+    fn eq(&self, other: &Self) -> bool {
+        self.comments == other.comments && self.value == other.value
+    }
 }
 
 impl<I> OptionalIndex<I> for Item
@@ -49,6 +69,7 @@ impl From<Value> for Item {
         Self {
             comments: Comments::new(),
             value: from,
+            key_style: None,
         }
     }
 }
@@ -87,7 +108,7 @@ impl Value {
     }
 
     /// Extracts the date-time value if it is a date-time.
-    pub fn as_date_time(&self) -> Option<&DateTime<FixedOffset>> {
+    pub fn as_date_time(&self) -> Option<&Datetime> {
         match self {
             Value::Primitive(Primitive::DateTime(date_time)) => Some(date_time),
             _ => None,
@@ -97,7 +118,7 @@ impl Value {
     /// Extracts the float value if it is a float.
     pub fn as_float(&self) -> Option<&Float> {
         match self {
-            Value::Primitive(Primitive::Float(float)) => Some(float),
+            Value::Primitive(Primitive::Float(float, _)) => Some(float),
             _ => None,
         }
     }
@@ -200,7 +221,7 @@ impl Value {
         match self {
             Value::Primitive(Primitive::String(_)) => "string",
             Value::Primitive(Primitive::Integer(_)) => "integer",
-            Value::Primitive(Primitive::Float(_)) => "float",
+            Value::Primitive(Primitive::Float(..)) => "float",
             Value::Primitive(Primitive::Boolean(_)) => "boolean",
             Value::Primitive(Primitive::DateTime(_)) => "datetime",
             Value::Array(_) => "array",
@@ -208,14 +229,19 @@ impl Value {
         }
     }
 
-    /// If key is empty - it is the top level table.
-    pub(crate) fn wrap<'a>(mut key: Key<'a>, item: Item) -> Self {
+    /// If key is empty - it is the top level table. The items keep the style
+    /// of their key segments.
+    pub(crate) fn wrap<'a>(mut key: Key<'a>, mut item: Item) -> Self {
         match key.pop() {
-            Some(segment) if !key.is_empty() => {
+            Some(segment) => {
+                item.key_style = Some(segment.style());
                 let value = Self::from(indexmap! { segment.into() => item });
-                Self::wrap(key, Item::from(value))
+                if key.is_empty() {
+                    value
+                } else {
+                    Self::wrap(key, Item::from(value))
+                }
             }
-            Some(segment) => Self::from(indexmap! { segment.into() => item }),
             None => item.value,
         }
     }
@@ -300,7 +326,7 @@ impl From<i64> for Value {
 impl From<Float> for Value {
     #[inline]
     fn from(from: Float) -> Self {
-        Self::Primitive(Primitive::from(from))
+        Self::Primitive(Primitive::Float(from, Literal::default()))
     }
 }
 
@@ -318,10 +344,17 @@ impl From<bool> for Value {
     }
 }
 
+impl From<Datetime> for Value {
+    #[inline]
+    fn from(from: Datetime) -> Self {
+        Self::Primitive(Primitive::from(from))
+    }
+}
+
 impl From<DateTime<FixedOffset>> for Value {
     #[inline]
     fn from(from: DateTime<FixedOffset>) -> Self {
-        Self::Primitive(Primitive::from(from))
+        Self::from(Datetime::from(from))
     }
 }
 
@@ -372,13 +405,69 @@ where
 }
 
 /// Primitive.
-#[derive(Clone, Debug, Display, From, PartialEq)]
+///
+/// Floats keep their literal, which is not compared.
+#[derive(Clone, Debug, From)]
 pub enum Primitive {
     String(Quoted<String>),
     Integer(Integer),
-    Float(Float),
+    #[from(ignore)]
+    Float(Float, Literal),
     Boolean(bool),
-    DateTime(DateTime<FixedOffset>),
+    DateTime(Datetime),
+}
+
+impl Primitive {
+    /// Tests whether the primitive is written otherwise than it is
+    /// represented: a negative non-decimal integer as a decimal one, or a
+    /// float without its literal which no longer spells the value.
+    pub(crate) fn is_respelled(&self) -> bool {
+        match self {
+            Self::Integer(integer) => {
+                i64::from(*integer) < 0 && !matches!(integer, Integer::Decimal(..))
+            }
+            Self::Float(float, literal) => literal
+                .as_str()
+                .is_some_and(|literal| !spells(literal, f64::from(float))),
+            _ => false,
+        }
+    }
+}
+
+impl Display for Primitive {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::String(string) => Display::fmt(string, f),
+            Self::Integer(integer) => Display::fmt(integer, f),
+            // The literal is stale once the value is changed.
+            Self::Float(float, literal) => match literal.as_str() {
+                Some(literal) if spells(literal, f64::from(float)) => f.write_str(literal),
+                _ => Display::fmt(float, f),
+            },
+            Self::Boolean(boolean) => Display::fmt(boolean, f),
+            Self::DateTime(date_time) => Display::fmt(date_time, f),
+        }
+    }
+}
+
+impl From<Float> for Primitive {
+    #[inline]
+    fn from(from: Float) -> Self {
+        Self::Float(from, Literal::default())
+    }
+}
+
+impl PartialEq for Primitive {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Integer(a), Self::Integer(b)) => a == b,
+            (Self::Float(a, _), Self::Float(b, _)) => a == b,
+            (Self::Boolean(a), Self::Boolean(b)) => a == b,
+            (Self::DateTime(a), Self::DateTime(b)) => a == b,
+            _ => false,
+        }
+    }
 }
 
 impl PartialOrd for Primitive {
@@ -386,7 +475,7 @@ impl PartialOrd for Primitive {
         match (self, other) {
             (Self::String(a), Self::String(b)) => a.partial_cmp(b),
             (Self::Integer(a), Self::Integer(b)) => a.partial_cmp(&b),
-            (Self::Float(a), Self::Float(b)) => a.partial_cmp(&b),
+            (Self::Float(a, _), Self::Float(b, _)) => a.partial_cmp(&b),
             (Self::Boolean(a), Self::Boolean(b)) => a.partial_cmp(&b),
             (Self::DateTime(a), Self::DateTime(b)) => a.partial_cmp(&b),
             _ => None,
@@ -395,31 +484,144 @@ impl PartialOrd for Primitive {
 }
 
 /// Integer.
-#[derive(Clone, Copy, Debug, Display)]
+#[derive(Clone, Copy, Debug)]
 pub enum Integer {
-    #[display(fmt = "{:#b}", _0)]
-    Binary(i64),
-    Decimal(i64),
-    #[display(fmt = "{:#o}", _0)]
-    Octal(i64),
-    #[display(fmt = "{:#x}", _0)]
-    Hex(i64),
+    Binary(i64, Digits),
+    Decimal(i64, Digits),
+    Octal(i64, Digits),
+    Hex(i64, Digits),
+}
+
+impl Integer {
+    /// Returns the digits of the integer.
+    pub fn digits(&self) -> Digits {
+        match *self {
+            Self::Binary(_, digits) => digits,
+            Self::Decimal(_, digits) => digits,
+            Self::Octal(_, digits) => digits,
+            Self::Hex(_, digits) => digits,
+        }
+    }
+}
+
+impl Display for Integer {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let value = i64::from(*self);
+        // Non-decimal integers can't have a sign, negative ones are decimal.
+        if value < 0 && !matches!(self, Self::Decimal(..)) {
+            return Display::fmt(&Self::from(value), f);
+        }
+        let Digits {
+            underscores,
+            leading_zeros,
+            uppercase,
+            plus,
+        } = self.digits();
+        let (prefix, digits) = match self {
+            Self::Binary(..) => ("0b", format!("{:b}", value)),
+            Self::Decimal(..) if value < 0 => ("-", (value as i128).abs().to_string()),
+            Self::Decimal(..) if plus => ("+", value.to_string()),
+            Self::Decimal(..) => ("", value.to_string()),
+            Self::Octal(..) => ("0o", format!("{:o}", value)),
+            Self::Hex(..) => ("0x", format!("{:x}", value)),
+        };
+        f.write_str(prefix)?;
+        let digits = "0".repeat(leading_zeros as usize) + &digits;
+        let len = digits.len();
+        for (index, c) in digits.chars().enumerate() {
+            let position = len - index - 1;
+            if position < 64 && uppercase & 1 << position != 0 {
+                f.write_char(c.to_ascii_uppercase())?;
+            } else {
+                f.write_char(c)?;
+            }
+            if position != 0 && position < 64 && underscores & 1 << position != 0 {
+                f.write_char('_')?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Integer {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (radix, mut rest): (_, &str) = match s.get(..2) {
+            Some("0b") => (2, &s[2..]),
+            Some("0o") => (8, &s[2..]),
+            Some("0x") => (16, &s[2..]),
+            _ => (10, s),
+        };
+        let mut digits = Digits::default();
+        let mut sign = "";
+        if radix == 10 {
+            if let Some(stripped) = rest.strip_prefix('+') {
+                digits.plus = true;
+                rest = stripped;
+            } else if let Some(stripped) = rest.strip_prefix('-') {
+                sign = "-";
+                rest = stripped;
+            }
+        }
+        // Each underscore must be surrounded by at least one digit on each
+        // side.
+        if rest.is_empty()
+            || rest.starts_with('_')
+            || rest.ends_with('_')
+            || rest.contains("__")
+            || !rest.chars().all(|c| c == '_' || c.is_digit(radix))
+        {
+            return Err(ErrorKind::Integer);
+        }
+        let mut position = 0;
+        for c in rest.chars().rev() {
+            if c == '_' {
+                if position < 64 {
+                    digits.underscores |= 1 << position;
+                }
+            } else {
+                position += 1;
+            }
+        }
+        let stripped: String = rest.chars().filter(|&c| c != '_').collect();
+        if radix == 10 && stripped.len() > 1 && stripped.starts_with('0') {
+            return Err(ErrorKind::Integer);
+        }
+        for (position, c) in stripped.chars().rev().take(64).enumerate() {
+            if c.is_ascii_uppercase() {
+                digits.uppercase |= 1 << position;
+            }
+        }
+        let value = i64::from_str_radix(&format!("{}{}", sign, stripped), radix)
+            .map_err(|_| ErrorKind::IntegerOverflow)?;
+        if radix != 10 {
+            let significant = stripped.trim_start_matches('0').len().max(1);
+            digits.leading_zeros = (stripped.len() - significant) as u8;
+        }
+        Ok(match radix {
+            2 => Self::Binary(value, digits),
+            8 => Self::Octal(value, digits),
+            16 => Self::Hex(value, digits),
+            _ => Self::Decimal(value, digits),
+        })
+    }
 }
 
 impl From<i64> for Integer {
     #[inline]
     fn from(from: i64) -> Self {
-        Self::Decimal(from)
+        Self::Decimal(from, Digits::default())
     }
 }
 
 impl From<Integer> for i64 {
     fn from(from: Integer) -> Self {
         match from {
-            Integer::Binary(v) => v,
-            Integer::Decimal(v) => v,
-            Integer::Octal(v) => v,
-            Integer::Hex(v) => v,
+            Integer::Binary(v, _) => v,
+            Integer::Decimal(v, _) => v,
+            Integer::Octal(v, _) => v,
+            Integer::Hex(v, _) => v,
         }
     }
 }
@@ -436,14 +638,121 @@ impl PartialOrd for Integer {
     }
 }
 
+/// Integer digits.
+///
+/// Remembers the spelling of the original integer, so that it is displayed
+/// back the same way.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Digits {
+    /// The underscore positions: the bit `n` is set if an underscore follows
+    /// the `n`-th digit, counting from the least significant one.
+    pub underscores: u64,
+    /// The number of leading zeros of a non-decimal integer.
+    pub leading_zeros: u8,
+    /// The uppercase hex digits: the bit `n` is set if the `n`-th digit,
+    /// counting from the least significant one, is uppercase.
+    pub uppercase: u64,
+    /// Whether the decimal integer has an explicit plus sign.
+    pub plus: bool,
+}
+
 /// Float.
-#[derive(Clone, Copy, Debug, Display)]
+///
+/// Decimal floats far from one are written in scientific notation.
+#[derive(Clone, Copy, Debug)]
 pub enum Float {
     Decimal(f64),
-    #[display(fmt = "{:#e}", _0)]
     Scientific(f64),
 }
 
+impl Float {
+    /// Parses the float along with its literal.
+    pub(crate) fn parse_literal(s: &str) -> Result<(Self, Literal), ErrorKind> {
+        Ok((s.parse()?, Literal(Some(s.into()))))
+    }
+}
+
+impl Display for Float {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let value = f64::from(self);
+        let magnitude = value.abs();
+        match self {
+            _ if value.is_nan() => f.write_str("nan"),
+            _ if value.is_infinite() && value > 0.0 => f.write_str("inf"),
+            _ if value.is_infinite() => f.write_str("-inf"),
+            Self::Decimal(..) if magnitude >= 1e21 || magnitude != 0.0 && magnitude < 1e-7 => {
+                write!(f, "{:e}", value)
+            }
+            Self::Decimal(..) => {
+                let decimal = value.to_string();
+                f.write_str(&decimal)?;
+                // Integral values still need a fractional part to stay floats.
+                if decimal.bytes().all(|b| b.is_ascii_digit() || b == b'-') {
+                    f.write_str(".0")?;
+                }
+                Ok(())
+            }
+            Self::Scientific(..) => write!(f, "{:e}", value),
+        }
+    }
+}
+
+impl FromStr for Float {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let is_sign = |c| c == '+' || c == '-';
+        let unsigned = s.strip_prefix(is_sign).unwrap_or(s);
+        if unsigned == "inf" || unsigned == "nan" {
+            let value = s.parse().map_err(|_| ErrorKind::Float)?;
+            return Ok(Self::Decimal(value));
+        }
+        let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+            Some(index) => (&unsigned[..index], Some(&unsigned[index + 1..])),
+            None => (unsigned, None),
+        };
+        let (integer, fraction) = match mantissa.find('.') {
+            Some(index) => (&mantissa[..index], Some(&mantissa[index + 1..])),
+            None => (mantissa, None),
+        };
+        let is_digits = |digits: &str| {
+            !digits.is_empty()
+                && !digits.starts_with('_')
+                && !digits.ends_with('_')
+                && !digits.contains("__")
+                && digits.chars().all(|c| c == '_' || c.is_ascii_digit())
+        };
+        // Leading zeros are not allowed in the integer part, but are allowed
+        // in the exponent part.
+        if !is_digits(integer)
+            || integer.len() > 1 && integer.starts_with('0')
+            || fraction.is_some_and(|fraction| !is_digits(fraction))
+            || exponent.is_some_and(|exponent| {
+                !is_digits(exponent.strip_prefix(is_sign).unwrap_or(exponent))
+            })
+            || fraction.is_none() && exponent.is_none()
+        {
+            return Err(ErrorKind::Float);
+        }
+        let stripped: String = s.chars().filter(|&c| c != '_').collect();
+        let value = stripped.parse().map_err(|_| ErrorKind::Float)?;
+        Ok(match exponent {
+            Some(_) => Self::Scientific(value),
+            None => Self::Decimal(value),
+        })
+    }
+}
+
+/// Tests whether the literal spells the value, any NaN spelling a NaN.
+fn spells(literal: &str, value: f64) -> bool {
+    let stripped: String = literal.chars().filter(|&c| c != '_').collect();
+    match stripped.parse::<f64>() {
+        Ok(parsed) if parsed.is_nan() => value.is_nan(),
+        Ok(parsed) => parsed.to_bits() == value.to_bits(),
+        Err(_) => false,
+    }
+}
+
 impl From<f64> for Float {
     #[inline]
     fn from(from: f64) -> Self {
@@ -453,7 +762,13 @@ impl From<f64> for Float {
 
 impl From<Float> for f64 {
     fn from(from: Float) -> f64 {
-        match from {
+        f64::from(&from)
+    }
+}
+
+impl From<&Float> for f64 {
+    fn from(from: &Float) -> f64 {
+        match *from {
             Float::Decimal(v) => v,
             Float::Scientific(v) => v,
         }
@@ -462,13 +777,28 @@ impl From<Float> for f64 {
 
 impl PartialEq for Float {
     fn eq(&self, other: &Self) -> bool {
-        f64::from(*self) == f64::from(*other)
+        f64::from(self) == f64::from(other)
     }
 }
 
 impl PartialOrd for Float {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        f64::from(*self).partial_cmp(&f64::from(*other))
+        f64::from(self).partial_cmp(&f64::from(other))
+    }
+}
+
+/// Literal.
+///
+/// The original spelling of a parsed float, so that it is displayed back
+/// byte for byte as long as it spells the value. Constructed floats have no
+/// literal.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Literal(Option<Box<str>>);
+
+impl Literal {
+    /// Extracts the string slice if there is one.
+    pub fn as_str(&self) -> Option<&str> {
+        self.0.as_deref()
     }
 }
 
@@ -532,11 +862,18 @@ impl<K: Into<String>, V: Into<Item>> FromIterator<(K, V)> for Table {
     }
 }
 
+/// A value conflicting with a previous one replaces it, as in maps. An empty
+/// key is the table itself, whose value is merged if it is a table.
 impl<'a> FromIterator<(Key<'a>, Value)> for Table {
     fn from_iter<I: IntoIterator<Item = (Key<'a>, Value)>>(iter: I) -> Self {
         let mut table = Self::new();
         for (key, value) in iter {
-            table.merge(Value::wrap(key, Item::from(value)));
+            let value = Value::wrap(key, Item::from(value));
+            if check_table(&table, &value).is_ok() {
+                merge_table(&mut table, value);
+            } else if let Value::Table(other) = value {
+                table.merge_with(other, &MergeOptions::new());
+            }
         }
         table
     }
@@ -544,73 +881,520 @@ impl<'a> FromIterator<(Key<'a>, Value)> for Table {
 
 #[cfg(feature = "toml")]
 mod toml {
-    use super::{Array, Primitive, Table, Value};
-
-    impl From<Value> for toml::Value {
-        fn from(from: Value) -> Self {
-            match from {
-                Value::Primitive(Primitive::String(string)) => Self::String(string.to_string()),
+    use super::{Array, Datetime, Primitive, Table, Value};
+    use crate::datetime::Error;
+    use std::convert::{TryFrom, TryInto};
+
+    /// Only date times can fail to convert, as the toml crate accepts some
+    /// that chrono can't hold and the other way around.
+    impl TryFrom<Value> for toml::Value {
+        type Error = Error;
+
+        fn try_from(from: Value) -> Result<Self, Self::Error> {
+            Ok(match from {
+                Value::Primitive(Primitive::String(string)) => Self::String(string.as_str().into()),
                 Value::Primitive(Primitive::Integer(integer)) => Self::Integer(integer.into()),
-                Value::Primitive(Primitive::Float(float)) => Self::Float(float.into()),
+                Value::Primitive(Primitive::Float(float, _)) => Self::Float(float.into()),
                 Value::Primitive(Primitive::Boolean(boolean)) => Self::Boolean(boolean),
-                // TODO:
-                // Value::Primitive(Primitive::DateTime(date_time)) => Self::Datetime(date_time),
-                Value::Array(array) => Self::Array(array.into()),
-                Value::Table(table) => Self::Table(table.into()),
-                _ => unimplemented!(),
+                Value::Primitive(Primitive::DateTime(date_time)) => {
+                    Self::Datetime(date_time.try_into()?)
+                }
+                Value::Array(array) => Self::Array(array.try_into()?),
+                Value::Table(table) => Self::Table(table.try_into()?),
+            })
+        }
+    }
+
+    impl TryFrom<Array> for toml::value::Array {
+        type Error = Error;
+
+        fn try_from(from: Array) -> Result<Self, Self::Error> {
+            from.0.into_iter().map(|v| v.value.try_into()).collect()
+        }
+    }
+
+    impl TryFrom<Table> for toml::value::Table {
+        type Error = Error;
+
+        fn try_from(from: Table) -> Result<Self, Self::Error> {
+            from.0
+                .into_iter()
+                .map(|(k, v)| Ok((k, v.value.try_into()?)))
+                .collect()
+        }
+    }
+
+    impl TryFrom<Datetime> for toml::value::Datetime {
+        type Error = Error;
+
+        fn try_from(from: Datetime) -> Result<Self, Self::Error> {
+            from.to_string().parse().map_err(|_| Error)
+        }
+    }
+
+    impl TryFrom<toml::Value> for Value {
+        type Error = Error;
+
+        fn try_from(from: toml::Value) -> Result<Self, Self::Error> {
+            Ok(match from {
+                toml::Value::String(string) => Self::from(string),
+                toml::Value::Integer(integer) => Self::from(integer),
+                toml::Value::Float(float) => Self::from(float),
+                toml::Value::Boolean(boolean) => Self::from(boolean),
+                toml::Value::Datetime(date_time) => Self::from(Datetime::try_from(date_time)?),
+                toml::Value::Array(array) => Self::Array(array.try_into()?),
+                toml::Value::Table(table) => Self::Table(table.try_into()?),
+            })
+        }
+    }
+
+    impl TryFrom<toml::value::Array> for Array {
+        type Error = Error;
+
+        fn try_from(from: toml::value::Array) -> Result<Self, Self::Error> {
+            from.into_iter().map(Value::try_from).collect()
+        }
+    }
+
+    impl TryFrom<toml::value::Table> for Table {
+        type Error = Error;
+
+        fn try_from(from: toml::value::Table) -> Result<Self, Self::Error> {
+            from.into_iter()
+                .map(|(k, v)| Ok((k, Value::try_from(v)?)))
+                .collect()
+        }
+    }
+
+    impl TryFrom<toml::value::Datetime> for Datetime {
+        type Error = Error;
+
+        fn try_from(from: toml::value::Datetime) -> Result<Self, Self::Error> {
+            from.to_string().parse()
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use crate::parse;
+        use chrono::{FixedOffset, NaiveDate, TimeZone};
+
+        #[test]
+        fn round_trip() {
+            let input = r#"a = "b"
+c = 0xff
+d = 1.5e3
+e = true
+f = 1979-05-27T00:32:00.999999-07:00
+g = 1979-05-27 07:32:00
+h = 1979-05-27
+i = 00:32:00
+j = [1, [2], { k = 3 }]
+
+[l]
+m = 'n'
+"#;
+            let table = parse(input).unwrap();
+            let toml = toml::value::Table::try_from(table.clone()).unwrap();
+            assert_eq!(
+                toml::Value::Table(toml.clone()),
+                input.parse::<toml::Value>().unwrap()
+            );
+            assert_eq!(toml["c"], toml::Value::Integer(255));
+            let table = Table::try_from(toml.clone()).unwrap();
+            assert_eq!(toml::value::Table::try_from(table), Ok(toml));
+        }
+
+        #[test]
+        fn error() {
+            for input in &[
+                "a = 1979-02-30",
+                "a = 24:00:00",
+                "a = 1979-05-27T00:32:00+99:00",
+            ] {
+                let toml = input.parse::<toml::Value>().unwrap();
+                assert_eq!(Value::try_from(toml), Err(Error), "{}", input);
             }
+            let date = NaiveDate::from_ymd_opt(10000, 1, 1).unwrap();
+            assert_eq!(
+                toml::value::Datetime::try_from(Datetime::from(date)),
+                Err(Error)
+            );
+            let offset = FixedOffset::east_opt(3723).unwrap();
+            let date_time = offset.with_ymd_and_hms(1979, 5, 27, 0, 32, 0).unwrap();
+            assert_eq!(
+                toml::value::Datetime::try_from(Datetime::from(date_time)),
+                Err(Error)
+            );
+        }
+    }
+}
+
+#[cfg(feature = "toml_edit")]
+mod toml_edit {
+    use super::{Array, Integer, Item, Primitive, Table, Value};
+    use crate::{
+        comment::{Comments, Kind},
+        datetime::Error,
+        quotes::{Quoted, Quotes},
+    };
+    use std::convert::{TryFrom, TryInto};
+
+    /// Only date times can fail to convert, as TOML can't hold some of the
+    /// ones chrono can and the other way around.
+    impl TryFrom<Table> for toml_edit::Document {
+        type Error = Error;
+
+        fn try_from(from: Table) -> Result<Self, Self::Error> {
+            Ok(Self::from(toml_edit::Table::try_from(from)?))
         }
     }
 
-    impl From<Array> for toml::value::Array {
-        fn from(from: Array) -> Self {
-            from.0.into_iter().map(|v| v.value.into()).collect()
+    impl TryFrom<Table> for toml_edit::Table {
+        type Error = Error;
+
+        fn try_from(from: Table) -> Result<Self, Self::Error> {
+            let mut table = Self::new();
+            for (key, item) in from.0 {
+                let (comments, value) = (item.comments, item.value);
+                let mut key = toml_edit::Key::new(key);
+                let item = match value {
+                    Value::Table(inner) => toml_edit::Item::Table(header(inner, &comments)?),
+                    Value::Array(array) if is_array_of_tables(&array) => {
+                        let mut array_of_tables = toml_edit::ArrayOfTables::new();
+                        for item in array.0 {
+                            let (comments, value) = (item.comments, item.value);
+                            if let Value::Table(inner) = value {
+                                let mut inner = header(inner, &comments)?;
+                                inner.set_implicit(false);
+                                array_of_tables.push(inner);
+                            }
+                        }
+                        toml_edit::Item::ArrayOfTables(array_of_tables)
+                    }
+                    value => {
+                        let mut value = toml_edit::Value::try_from(value)?;
+                        let pre = comments.pre().to_string();
+                        if !pre.is_empty() {
+                            key.decor_mut().set_prefix(pre);
+                        }
+                        let post = comments.post().to_string();
+                        if !post.is_empty() {
+                            value.decor_mut().set_suffix(post);
+                        }
+                        toml_edit::Item::Value(value)
+                    }
+                };
+                table.insert_formatted(&key, item);
+            }
+            // A table of only sub-tables needs no header of its own.
+            let is_implicit = !table.is_empty() && table.iter().all(|(_, item)| !item.is_value());
+            table.set_implicit(is_implicit);
+            Ok(table)
         }
     }
 
-    impl From<Table> for toml::value::Table {
-        fn from(from: Table) -> Self {
+    impl TryFrom<Table> for toml_edit::InlineTable {
+        type Error = Error;
+
+        fn try_from(from: Table) -> Result<Self, Self::Error> {
             from.0
                 .into_iter()
-                .map(|(k, v)| (k, v.value.into()))
+                .map(|(k, v)| Ok((k, toml_edit::Value::try_from(v.value)?)))
+                .collect()
+        }
+    }
+
+    impl TryFrom<Array> for toml_edit::Array {
+        type Error = Error;
+
+        fn try_from(from: Array) -> Result<Self, Self::Error> {
+            let mut array = Self::new();
+            if from.iter().all(|item| item.comments.is_empty()) {
+                for item in from.0 {
+                    array.push_formatted(item.value.try_into()?);
+                }
+                return Ok(array);
+            }
+            // Each element on its own line, its post comment after the comma.
+            let mut post = String::new();
+            for item in from.0 {
+                let (comments, value) = (item.comments, item.value);
+                let mut prefix = post;
+                prefix.push('\n');
+                for comment in comments.iter() {
+                    if let Kind::Pre(comment) = comment {
+                        prefix.push_str(&format!("    #{}\n", comment));
+                    }
+                }
+                prefix.push_str("    ");
+                post = comments.post().to_string();
+                let mut value = toml_edit::Value::try_from(value)?;
+                value.decor_mut().set_prefix(prefix);
+                array.push_formatted(value);
+            }
+            array.set_trailing_comma(true);
+            array.set_trailing(post + "\n");
+            Ok(array)
+        }
+    }
+
+    impl TryFrom<Value> for toml_edit::Value {
+        type Error = Error;
+
+        fn try_from(from: Value) -> Result<Self, Self::Error> {
+            match from {
+                Value::Primitive(primitive) => {
+                    // Parses the representation, so that it is kept as is.
+                    if let Ok(mut value) = primitive.to_string().parse::<Self>() {
+                        value.decor_mut().clear();
+                        return Ok(value);
+                    }
+                    match primitive {
+                        Primitive::String(string) => Ok(Self::from(string.as_str())),
+                        Primitive::Integer(integer) => Ok(Self::from(i64::from(integer))),
+                        Primitive::Float(float, _) => Ok(Self::from(f64::from(float))),
+                        Primitive::Boolean(boolean) => Ok(Self::from(boolean)),
+                        Primitive::DateTime(_) => Err(Error),
+                    }
+                }
+                Value::Array(array) => Ok(Self::Array(array.try_into()?)),
+                Value::Table(table) => Ok(Self::InlineTable(table.try_into()?)),
+            }
+        }
+    }
+
+    impl TryFrom<toml_edit::Document> for Table {
+        type Error = Error;
+
+        fn try_from(from: toml_edit::Document) -> Result<Self, Self::Error> {
+            Self::try_from(from.as_table())
+        }
+    }
+
+    impl TryFrom<toml_edit::Table> for Table {
+        type Error = Error;
+
+        fn try_from(from: toml_edit::Table) -> Result<Self, Self::Error> {
+            Self::try_from(&from)
+        }
+    }
+
+    impl TryFrom<&toml_edit::Table> for Table {
+        type Error = Error;
+
+        fn try_from(from: &toml_edit::Table) -> Result<Self, Self::Error> {
+            let mut table = Self::new();
+            for (key, item) in from.iter() {
+                let item = match item {
+                    toml_edit::Item::None => continue,
+                    toml_edit::Item::Value(value) => {
+                        let mut comments = Comments::new();
+                        if let Some(decor) = from.key_decor(key) {
+                            comments.extend(lines(decor.prefix()).map(Kind::Pre));
+                        }
+                        comments.extend(lines(value.decor().suffix()).map(Kind::Post));
+                        Item::new(comments, Value::try_from(value.clone())?)
+                    }
+                    toml_edit::Item::Table(inner) => {
+                        Item::new(decor(inner.decor()), Value::from(Self::try_from(inner)?))
+                    }
+                    toml_edit::Item::ArrayOfTables(array_of_tables) => {
+                        let array = array_of_tables
+                            .iter()
+                            .map(|inner| {
+                                let value = Value::from(Self::try_from(inner)?);
+                                Ok(Item::new(decor(inner.decor()), value))
+                            })
+                            .collect::<Result<Array, _>>()?;
+                        Item::from(Value::from(array))
+                    }
+                };
+                table.insert(key.to_owned(), item);
+            }
+            Ok(table)
+        }
+    }
+
+    impl TryFrom<toml_edit::InlineTable> for Table {
+        type Error = Error;
+
+        fn try_from(from: toml_edit::InlineTable) -> Result<Self, Self::Error> {
+            from.into_iter()
+                .map(|(k, v)| Ok((k.as_str().to_owned(), Value::try_from(v)?)))
                 .collect()
         }
     }
 
-    // impl From<toml::Value> for Value {
-    //     fn from(from: toml::Value) -> Value {
-    //         match from {
-    //             toml::Value::String(string) => {
-    //                 Self::Primitive(Primitive::String(string.to_string()))
-    //             }
-    //             toml::Value::Integer(integer) => {
-    //                 Self::Primitive(Primitive::Integer(integer.into()))
-    //             }
-    //             toml::Value::Float(float) => Value::Primitive(Primitive::Float(float.into())),
-    //             toml::Value::Boolean(boolean) => Value::Primitive(Primitive::Boolean(boolean)),
-    //             // toml::Value::Datetime(date_time) => {
-    //             //     Self::Primitive(Primitive::DateTime(date_time))
-    //             // }
-    //             toml::Value::Array(array) => Self::Array(array.into()),
-    //             toml::Value::Table(table) => Self::Table(table.into()),
-    //             _ => unimplemented!(),
-    //         }
-    //     }
-    // }
-
-    // impl From<toml::value::Array> for Array {
-    //     fn from(from: toml::value::Array) -> Array {
-    //         from.into_iter().map(|v| v.into()).collect()
-    //     }
-    // }
-
-    // impl From<toml::value::Table> for Table {
-    //     fn from(from: toml::value::Table) -> Table {
-    //         from.into_iter()
-    //             .map(|(k, v)| (k.into(), v.into()))
-    //             .collect()
-    //     }
-    // }
+    impl TryFrom<toml_edit::Array> for Array {
+        type Error = Error;
+
+        fn try_from(from: toml_edit::Array) -> Result<Self, Self::Error> {
+            let mut items: Vec<Item> = Vec::with_capacity(from.len());
+            let trailing = from.trailing().as_str().map(String::from);
+            for value in from {
+                let mut comments = Comments::new();
+                let mut prefix = value.decor().prefix().and_then(|raw| raw.as_str());
+                // The comment on the first line follows the previous element.
+                if let (Some(previous), Some(raw)) = (items.last_mut(), prefix) {
+                    let (first, rest) = raw.split_at(raw.find('\n').unwrap_or(raw.len()));
+                    previous.comments.extend(comment(first).map(Kind::Post));
+                    prefix = Some(rest);
+                }
+                let pre = prefix.into_iter().flat_map(str::lines).filter_map(comment);
+                comments.extend(pre.map(Kind::Pre));
+                comments.extend(lines(value.decor().suffix()).map(Kind::Post));
+                items.push(Item::new(comments, Value::try_from(value)?));
+            }
+            if let (Some(last), Some(trailing)) = (items.last_mut(), trailing) {
+                let first = trailing.lines().next().unwrap_or_default();
+                last.comments.extend(comment(first).map(Kind::Post));
+            }
+            Ok(Self::from(items))
+        }
+    }
+
+    impl TryFrom<toml_edit::Value> for Value {
+        type Error = Error;
+
+        fn try_from(from: toml_edit::Value) -> Result<Self, Self::Error> {
+            Ok(match from {
+                toml_edit::Value::String(string) => {
+                    let repr = string.display_repr();
+                    let is_single = repr.starts_with('\'');
+                    let is_multi_line = repr.starts_with("'''") || repr.starts_with(r#"""""#);
+                    let value = string.into_value();
+                    let quotes = if is_single {
+                        Quotes::Single(value)
+                    } else {
+                        Quotes::Double(value)
+                    };
+                    Self::from(if is_multi_line {
+                        Quoted::MultiLine(quotes)
+                    } else {
+                        Quoted::SingleLine(quotes)
+                    })
+                }
+                toml_edit::Value::Integer(integer) => Self::from(
+                    integer
+                        .display_repr()
+                        .parse()
+                        .unwrap_or_else(|_| Integer::from(*integer.value())),
+                ),
+                toml_edit::Value::Float(float) => {
+                    match super::Float::parse_literal(&float.display_repr()) {
+                        Ok((value, literal)) => Self::Primitive(Primitive::Float(value, literal)),
+                        Err(_) => Self::from(*float.value()),
+                    }
+                }
+                toml_edit::Value::Boolean(boolean) => Self::from(boolean.into_value()),
+                toml_edit::Value::Datetime(date_time) => {
+                    Self::from(date_time.display_repr().parse::<super::Datetime>()?)
+                }
+                toml_edit::Value::Array(array) => Self::Array(array.try_into()?),
+                toml_edit::Value::InlineTable(table) => Self::Table(table.try_into()?),
+            })
+        }
+    }
+
+    /// Tests whether the array is a non-empty array of tables.
+    fn is_array_of_tables(array: &Array) -> bool {
+        !array.is_empty() && array.iter().all(|item| item.is_table())
+    }
+
+    /// Converts the table to a header table with the comments in its decor.
+    fn header(from: Table, comments: &Comments) -> Result<toml_edit::Table, Error> {
+        let mut table = toml_edit::Table::try_from(from)?;
+        if !comments.is_empty() {
+            table.set_implicit(false);
+            let decor = table.decor_mut();
+            decor.set_prefix(format!("\n{}", comments.pre()));
+            decor.set_suffix(comments.post().to_string());
+        }
+        Ok(table)
+    }
+
+    /// Returns the comments of the table header decor.
+    fn decor(decor: &toml_edit::Decor) -> Comments {
+        let mut comments = Comments::new();
+        comments.extend(lines(decor.prefix()).map(Kind::Pre));
+        comments.extend(lines(decor.suffix()).map(Kind::Post));
+        comments
+    }
+
+    /// Returns the comments of the lines of the raw decor.
+    fn lines(raw: Option<&toml_edit::RawString>) -> impl Iterator<Item = String> + '_ {
+        raw.and_then(|raw| raw.as_str())
+            .into_iter()
+            .flat_map(str::lines)
+            .filter_map(comment)
+    }
+
+    /// Returns the comment of the line if there is one.
+    fn comment(line: &str) -> Option<String> {
+        let index = line.find('#')?;
+        Some(line[index + 1..].to_owned())
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use crate::{datetime::Datetime, parse};
+        use chrono::NaiveDate;
+        use std::iter::FromIterator;
+
+        #[test]
+        fn round_trip() {
+            let input = r#"# a
+a = 'b' # b
+c = 0xFF
+d = 1.5e3
+e = 1979-05-27 07:32:00
+f = [
+    # c
+    1, # d
+    2,
+]
+
+# e
+[g] # f
+h = """i"""
+
+[[j]]
+k = 1
+
+[l.m]
+n = [1, [2]]
+"#;
+            let table = parse(input).unwrap();
+            let document = toml_edit::Document::try_from(table.clone()).unwrap();
+            assert_eq!(document.to_string(), input);
+            assert_eq!(Table::try_from(document), Ok(table.clone()));
+            let document = input.parse::<toml_edit::Document>().unwrap();
+            assert_eq!(Table::try_from(document), Ok(table));
+        }
+
+        #[test]
+        fn leap_second() {
+            let input = "a = 1979-05-27T23:59:60Z\n";
+            let document = input.parse::<toml_edit::Document>().unwrap();
+            let table = Table::try_from(document).unwrap();
+            assert_eq!(table, parse(input).unwrap());
+            let document = toml_edit::Document::try_from(table).unwrap();
+            assert_eq!(document.to_string(), input);
+        }
+
+        #[test]
+        fn error() {
+            let date = NaiveDate::from_ymd_opt(10000, 1, 1).unwrap();
+            let table = Table::from_iter(vec![("a", Value::from(Datetime::from(date)))]);
+            assert_eq!(toml_edit::Document::try_from(table).err(), Some(Error));
+            let string = Quoted::SingleLine(Quotes::Single("it's".to_owned()));
+            let value = toml_edit::Value::try_from(Value::from(string)).unwrap();
+            assert_eq!(value.to_string(), r#""it's""#);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -620,21 +1404,131 @@ mod test {
     #[test]
     fn test() {
         let a = Primitive::Boolean(true);
-        let b = Primitive::Integer(Integer::Decimal(9));
+        let b = Primitive::Integer(Integer::from(9));
         assert_eq!(a.partial_cmp(&b), None);
-        let a = Primitive::Integer(Integer::Decimal(9));
-        let b = Primitive::Integer(Integer::Hex(9));
+        let a = Primitive::Integer(Integer::from(9));
+        let b = Primitive::Integer(Integer::Hex(9, Digits::default()));
         assert_eq!(a.partial_cmp(&b), Some(Ordering::Equal));
-        let a = Primitive::Integer(Integer::Decimal(9));
-        let b = Primitive::Integer(Integer::Hex(8));
+        let a = Primitive::Integer(Integer::from(9));
+        let b = Primitive::Integer(Integer::Hex(8, Digits::default()));
         assert_eq!(a.partial_cmp(&b), Some(Ordering::Greater));
-        let a = Primitive::Integer(Integer::Decimal(9));
-        let b = Primitive::Integer(Integer::Hex(10));
+        let a = Primitive::Integer(Integer::from(9));
+        let b = Primitive::Integer(Integer::Hex(10, Digits::default()));
         assert_eq!(a.partial_cmp(&b), Some(Ordering::Less));
 
         println!("0: {:?}", a.partial_cmp(&b));
     }
 
+    #[test]
+    fn integer() {
+        let round_trip = |input: &str| input.parse::<Integer>().unwrap().to_string();
+        assert_eq!("0xff".parse(), Ok(Integer::Hex(255, Digits::default())));
+        assert_eq!("0b101".parse(), Ok(Integer::Binary(5, Digits::default())));
+        assert_eq!("0o755".parse(), Ok(Integer::Octal(493, Digits::default())));
+        assert_eq!("-17".parse(), Ok(Integer::from(-17)));
+        assert_eq!(round_trip("1_000_000"), "1_000_000");
+        assert_eq!(round_trip("+99"), "+99");
+        assert_eq!(round_trip("-5_349_221"), "-5_349_221");
+        assert_eq!(round_trip("0xdead_BEEF"), "0xdead_BEEF");
+        assert_eq!(round_trip("0xFF"), "0xFF");
+        assert_eq!(round_trip("0x00ff"), "0x00ff");
+        assert_eq!(round_trip("0o0_755"), "0o0_755");
+        assert_eq!(round_trip("0b1101_0110"), "0b1101_0110");
+        assert_eq!(round_trip("0x0"), "0x0");
+        assert_eq!(round_trip("-9223372036854775808"), "-9223372036854775808");
+        assert_eq!("_1".parse::<Integer>(), Err(ErrorKind::Integer));
+        assert_eq!("1_".parse::<Integer>(), Err(ErrorKind::Integer));
+        assert_eq!("1__0".parse::<Integer>(), Err(ErrorKind::Integer));
+        assert_eq!("0x_ff".parse::<Integer>(), Err(ErrorKind::Integer));
+        assert_eq!("0123".parse::<Integer>(), Err(ErrorKind::Integer));
+        assert_eq!(
+            "9223372036854775808".parse::<Integer>(),
+            Err(ErrorKind::IntegerOverflow)
+        );
+        assert_eq!(
+            "0x1_0000_0000_0000_0000".parse::<Integer>(),
+            Err(ErrorKind::IntegerOverflow)
+        );
+        let digits = "0x0f_f".parse::<Integer>().unwrap().digits();
+        assert_eq!(Integer::Hex(-255, digits).to_string(), "-255");
+        assert_eq!(Integer::Binary(-5, Digits::default()).to_string(), "-5");
+        assert_eq!(Integer::Octal(-8, Digits::default()).to_string(), "-8");
+    }
+
+    #[test]
+    fn key_value_pairs() {
+        let key = |key: &'static str| Key::from_iter(key.split('.'));
+        let table = Table::from_iter(vec![
+            (key("a.b"), Value::from(1)),
+            (key("a.c"), Value::from(2)),
+            (key("a.b"), Value::from(3)),
+            (key("d"), Value::from(4)),
+            (key("d.e"), Value::from(5)),
+        ]);
+        let expected = crate::parse("a = { b = 3, c = 2 }\nd = { e = 5 }").unwrap();
+        assert_eq!(table, expected);
+    }
+
+    #[test]
+    fn float() {
+        let primitive = |input: &str| {
+            let (float, literal) = Float::parse_literal(input).unwrap();
+            Primitive::Float(float, literal)
+        };
+        let round_trip = |input: &str| primitive(input).to_string();
+        for input in &[
+            "+1.0",
+            "3.1415",
+            "-0.01",
+            "5e+22",
+            "1e06",
+            "-2E-2",
+            "6.626e-34",
+            "224_617.445_991",
+            "1.50",
+            "inf",
+            "+inf",
+            "-inf",
+            "nan",
+            "+nan",
+            "-nan",
+        ] {
+            assert_eq!(&round_trip(input), input);
+        }
+        assert!(matches!("1e10".parse(), Ok(Float::Scientific(v)) if v == 1e10));
+        assert!(matches!("1_000.5".parse(), Ok(Float::Decimal(v)) if v == 1000.5));
+        assert!(matches!("-inf".parse(), Ok(Float::Decimal(v)) if v == f64::NEG_INFINITY));
+        assert!(matches!("nan".parse(), Ok(Float::Decimal(v)) if v.is_nan()));
+        for input in &[
+            "1.", ".5", "1e", "01.5", "1_.5", "1._5", "1__0.5", "1e_5", "1e+-5", "1",
+        ] {
+            assert_eq!(input.parse::<Float>(), Err(ErrorKind::Float), "{}", input);
+        }
+        let mut float = primitive("1_000.50");
+        if let Primitive::Float(Float::Decimal(value), _) = &mut float {
+            *value = 2.5;
+        }
+        assert_eq!(float.to_string(), "2.5");
+        let mut float = primitive("-0.0");
+        assert_eq!(float.to_string(), "-0.0");
+        if let Primitive::Float(Float::Decimal(value), _) = &mut float {
+            *value = 0.0;
+        }
+        assert_eq!(float.to_string(), "0.0");
+        assert_eq!(primitive("1.50"), Primitive::from(Float::from(1.5)));
+        // Floats are copied without their literal.
+        let copy = *Value::Primitive(primitive("1.50")).as_float().unwrap();
+        assert_eq!(Primitive::from(copy).to_string(), "1.5");
+        assert_eq!(Float::from(1.0).to_string(), "1.0");
+        assert_eq!(Float::from(-2.5).to_string(), "-2.5");
+        assert_eq!(Float::Scientific(1e10).to_string(), "1e10");
+        assert_eq!(Float::from(f64::NEG_INFINITY).to_string(), "-inf");
+        assert_eq!(Float::from(1e20).to_string(), "100000000000000000000.0");
+        assert_eq!(Float::from(1e300).to_string(), "1e300");
+        assert_eq!(Float::from(-2.5e-8).to_string(), "-2.5e-8");
+        assert_eq!(Float::from(1.5e-7).to_string(), "0.00000015");
+    }
+
     #[test]
     fn array() {
         let value = Value::from_iter(vec![Value::from(true), Value::from(true)]);